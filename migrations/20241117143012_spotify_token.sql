@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE
+    IF NOT EXISTS spotify_token (
+        id SMALLINT PRIMARY KEY DEFAULT 1 CHECK (id = 1), -- Only one account is connected
+        token BYTEA NOT NULL, -- Encrypted JSON of the rspotify Token
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+    );
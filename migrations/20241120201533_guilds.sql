@@ -0,0 +1,27 @@
+-- Every guild has its own users and Spotify account
+-- Rows from before multi-guild support get guild_id 0 until adopted on startup
+ALTER TABLE users
+ADD COLUMN guild_id BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE users
+DROP CONSTRAINT users_pkey;
+
+ALTER TABLE users
+ADD PRIMARY KEY (guild_id, id);
+
+ALTER TABLE users
+ALTER COLUMN guild_id
+DROP DEFAULT;
+
+ALTER TABLE spotify_token
+DROP COLUMN id;
+
+ALTER TABLE spotify_token
+ADD COLUMN guild_id BIGINT NOT NULL DEFAULT 0 PRIMARY KEY;
+
+ALTER TABLE spotify_token
+ALTER COLUMN guild_id
+DROP DEFAULT;
+
+ALTER TABLE spotify_token
+ADD COLUMN owner_id BIGINT; -- Discord User Id of who authenticated
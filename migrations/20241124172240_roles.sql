@@ -0,0 +1,56 @@
+-- Named roles grouping the capabilities a user has
+CREATE TABLE
+    IF NOT EXISTS roles (
+        name TEXT PRIMARY KEY, -- Name shown to users
+        level SMALLINT NOT NULL -- Rank of the role; Higher levels outrank lower ones
+    );
+
+CREATE TABLE
+    IF NOT EXISTS role_capabilities (
+        role TEXT NOT NULL REFERENCES roles (name) ON UPDATE CASCADE ON DELETE CASCADE,
+        capability TEXT NOT NULL, -- permissions::Capability
+        PRIMARY KEY (role, capability)
+    );
+
+INSERT INTO
+    roles (name, level)
+VALUES
+    ('guest', 0),
+    ('listener', 1),
+    ('dj', 2),
+    ('moderator', 3) ON CONFLICT DO NOTHING;
+
+INSERT INTO
+    role_capabilities (role, capability)
+VALUES
+    ('listener', 'queue'),
+    ('dj', 'queue'),
+    ('dj', 'skip'),
+    ('dj', 'previous'),
+    ('dj', 'volume'),
+    ('moderator', 'queue'),
+    ('moderator', 'skip'),
+    ('moderator', 'previous'),
+    ('moderator', 'volume'),
+    ('moderator', 'freeze'),
+    ('moderator', 'manage_users') ON CONFLICT DO NOTHING;
+
+-- Replace the numeric permission with a role
+ALTER TABLE users
+ADD COLUMN role TEXT REFERENCES roles (name) ON UPDATE CASCADE;
+
+UPDATE users
+SET
+    role = CASE
+        WHEN permission >= 3 THEN 'moderator'
+        WHEN permission = 2 THEN 'dj'
+        WHEN permission = 1 THEN 'listener'
+        ELSE 'guest'
+    END;
+
+ALTER TABLE users
+ALTER COLUMN role
+SET NOT NULL;
+
+ALTER TABLE users
+DROP COLUMN permission;
@@ -0,0 +1,8 @@
+-- Gives every member of a Discord role one of the bot's roles
+CREATE TABLE
+    IF NOT EXISTS role_grants (
+        guild_id BIGINT NOT NULL, -- Discord Guild Id
+        discord_role_id BIGINT NOT NULL, -- Discord Role Id
+        role TEXT NOT NULL REFERENCES roles (name) ON UPDATE CASCADE ON DELETE CASCADE,
+        PRIMARY KEY (guild_id, discord_role_id)
+    );
@@ -0,0 +1,17 @@
+-- Settings that each guild can configure
+CREATE TABLE
+    IF NOT EXISTS guild_settings (
+        guild_id BIGINT PRIMARY KEY, -- Discord Guild Id
+        vote_skip BOOLEAN NOT NULL DEFAULT FALSE, -- Whether /next starts a vote
+        vote_threshold SMALLINT NOT NULL DEFAULT 3, -- Votes needed to skip
+        vote_percentage SMALLINT, -- Percentage of the owner's voice channel needed to skip
+        vote_timeout INTEGER NOT NULL DEFAULT 60, -- Seconds a vote stays open
+        vote_voice_only BOOLEAN NOT NULL DEFAULT FALSE -- Only count voters in the owner's voice channel
+    );
+
+-- Skipping without a vote, and changing settings
+INSERT INTO
+    role_capabilities (role, capability)
+VALUES
+    ('moderator', 'force_skip'),
+    ('moderator', 'manage_settings') ON CONFLICT DO NOTHING;
@@ -0,0 +1,16 @@
+-- History of everything played or queued through the bot
+CREATE TABLE
+    IF NOT EXISTS plays (
+        id BIGSERIAL PRIMARY KEY,
+        guild_id BIGINT NOT NULL, -- Discord Guild Id
+        track_id TEXT NOT NULL, -- Spotify URI of the track or episode
+        name TEXT NOT NULL,
+        artists TEXT[] NOT NULL,
+        url TEXT NOT NULL,
+        requester BIGINT, -- Discord User Id; NULL if it wasn't queued through the bot
+        queued_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+        started_at TIMESTAMPTZ, -- NULL until it starts playing
+        ended TEXT -- 'finished' or 'skipped'; NULL while playing
+    );
+
+CREATE INDEX IF NOT EXISTS plays_guild_started ON plays (guild_id, started_at DESC);
@@ -0,0 +1,21 @@
+-- Limits on how many songs can be requested in a period; No row means unlimited
+CREATE TABLE
+    IF NOT EXISTS role_quotas (
+        guild_id BIGINT NOT NULL, -- Discord Guild Id
+        role TEXT NOT NULL REFERENCES roles (name) ON UPDATE CASCADE ON DELETE CASCADE,
+        max_requests INTEGER NOT NULL, -- Songs allowed each period
+        period INTEGER NOT NULL, -- Seconds
+        PRIMARY KEY (guild_id, role)
+    );
+
+-- Overrides the quota of the user's role
+CREATE TABLE
+    IF NOT EXISTS user_quotas (
+        guild_id BIGINT NOT NULL, -- Discord Guild Id
+        user_id BIGINT NOT NULL, -- Discord User Id
+        max_requests INTEGER NOT NULL, -- Songs allowed each period
+        period INTEGER NOT NULL, -- Seconds
+        PRIMARY KEY (guild_id, user_id)
+    );
+
+CREATE INDEX IF NOT EXISTS plays_guild_requester ON plays (guild_id, requester, queued_at);
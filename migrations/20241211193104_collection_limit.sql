@@ -0,0 +1,3 @@
+-- Most songs queued from a single album or playlist
+ALTER TABLE guild_settings
+ADD COLUMN IF NOT EXISTS collection_limit SMALLINT NOT NULL DEFAULT 25;
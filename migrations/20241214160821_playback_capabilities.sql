@@ -0,0 +1,10 @@
+-- Pausing, shuffling and repeating, used by the control panel
+INSERT INTO
+    role_capabilities (role, capability)
+VALUES
+    ('dj', 'pause'),
+    ('dj', 'shuffle'),
+    ('dj', 'repeat'),
+    ('moderator', 'pause'),
+    ('moderator', 'shuffle'),
+    ('moderator', 'repeat') ON CONFLICT DO NOTHING;
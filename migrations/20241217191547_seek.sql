@@ -0,0 +1,6 @@
+-- Jumping to a time in the current song
+INSERT INTO
+    role_capabilities (role, capability)
+VALUES
+    ('dj', 'seek'),
+    ('moderator', 'seek') ON CONFLICT DO NOTHING;
@@ -0,0 +1,3 @@
+-- Device /play starts playback on when nothing is playing
+ALTER TABLE guild_settings
+ADD COLUMN IF NOT EXISTS preferred_device TEXT;
@@ -0,0 +1,5 @@
+-- Removing and moving anyone's requests
+INSERT INTO
+    role_capabilities (role, capability)
+VALUES
+    ('moderator', 'manage_queue') ON CONFLICT DO NOTHING;
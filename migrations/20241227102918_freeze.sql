@@ -0,0 +1,17 @@
+-- Freezing survives restarts
+ALTER TABLE guild_settings
+ADD COLUMN IF NOT EXISTS frozen BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN IF NOT EXISTS frozen_until TIMESTAMPTZ; -- NULL if frozen until turned off
+
+-- Times every week that playback is frozen
+CREATE TABLE
+    IF NOT EXISTS freeze_schedules (
+        id BIGSERIAL PRIMARY KEY,
+        guild_id BIGINT NOT NULL, -- Discord Guild Id
+        days SMALLINT NOT NULL, -- Bit for each day the window starts on; Monday is the lowest bit
+        starts TIME NOT NULL,
+        ends TIME NOT NULL, -- Before starts if the window crosses midnight
+        timezone TEXT NOT NULL -- IANA name, like America/New_York
+    );
+
+CREATE INDEX IF NOT EXISTS freeze_schedules_guild ON freeze_schedules (guild_id);
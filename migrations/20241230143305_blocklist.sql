@@ -0,0 +1,9 @@
+-- Songs, artists and albums that can't be queued
+CREATE TABLE
+    IF NOT EXISTS blocklist (
+        guild_id BIGINT NOT NULL, -- Discord Guild Id
+        kind TEXT NOT NULL, -- 'track', 'artist', 'album' or 'explicit'
+        item_id TEXT NOT NULL, -- Spotify Id; Empty for 'explicit'
+        name TEXT NOT NULL, -- Shown when listing the blocklist
+        PRIMARY KEY (guild_id, kind, item_id)
+    );
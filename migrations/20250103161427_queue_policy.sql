@@ -0,0 +1,12 @@
+-- What can be queued, beyond the blocklist
+ALTER TABLE guild_settings
+ADD COLUMN IF NOT EXISTS allow_explicit BOOLEAN NOT NULL DEFAULT TRUE,
+ADD COLUMN IF NOT EXISTS max_duration INT, -- Seconds; NULL for no limit
+ADD COLUMN IF NOT EXISTS allow_podcasts BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Explicit content was blocked through the blocklist before it was a setting
+INSERT INTO guild_settings (guild_id, allow_explicit)
+SELECT guild_id, FALSE FROM blocklist WHERE kind = 'explicit'
+ON CONFLICT (guild_id) DO UPDATE SET allow_explicit = FALSE;
+
+DELETE FROM blocklist WHERE kind = 'explicit';
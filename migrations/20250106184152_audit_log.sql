@@ -0,0 +1,14 @@
+-- Who changed what, for /audit
+CREATE TABLE
+    IF NOT EXISTS audit_log (
+        id BIGSERIAL PRIMARY KEY,
+        guild_id BIGINT NOT NULL, -- Discord Guild Id
+        actor_id BIGINT NOT NULL, -- Discord User Id of who did it
+        action TEXT NOT NULL, -- audit::AuditAction
+        target TEXT, -- What it was done to, like a song or user; NULL if nothing in particular
+        old_value TEXT,
+        new_value TEXT,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+    );
+
+CREATE INDEX IF NOT EXISTS audit_log_guild_created ON audit_log (guild_id, created_at DESC);
@@ -0,0 +1,12 @@
+-- Where the bot posts what happens in a guild, and which events it posts
+CREATE TABLE
+    IF NOT EXISTS log_settings (
+        guild_id BIGINT PRIMARY KEY, -- Discord Guild Id
+        channel_id BIGINT, -- Discord Channel Id; NULL for no log channel
+        log_queue BOOLEAN NOT NULL DEFAULT TRUE, -- Songs added with /play
+        log_skips BOOLEAN NOT NULL DEFAULT TRUE,
+        log_previous BOOLEAN NOT NULL DEFAULT TRUE,
+        log_freeze BOOLEAN NOT NULL DEFAULT TRUE, -- Freezing, unfreezing and schedules
+        log_permissions BOOLEAN NOT NULL DEFAULT TRUE, -- Users and role grants
+        log_authentication BOOLEAN NOT NULL DEFAULT TRUE
+    );
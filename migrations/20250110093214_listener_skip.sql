@@ -0,0 +1,6 @@
+-- Level 1 users could skip and go back before roles replaced levels, so listeners keep that
+INSERT INTO
+    role_capabilities (role, capability)
+VALUES
+    ('listener', 'skip'),
+    ('listener', 'previous') ON CONFLICT DO NOTHING;
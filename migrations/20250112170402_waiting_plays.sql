@@ -0,0 +1,3 @@
+-- Requests still waiting in the bot's own queue, which is lost on restart
+ALTER TABLE plays
+ADD COLUMN IF NOT EXISTS waiting BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,259 @@
+use std::{collections::HashSet, fmt};
+
+use chrono::{DateTime, Utc};
+
+use poise::serenity_prelude::{self as serenity, ComponentInteraction, GuildId, RoleId, UserId};
+use rspotify::prelude::OAuthClient;
+
+use crate::database::{db_get_token_owner, db_get_user_role};
+use crate::permissions::{Capability, Role};
+use crate::{format_period, Context, Data, Error};
+
+/// Told to users when the server hasn't connected Spotify
+pub const UNAUTHORIZED: &str =
+    "The application isn't authenticated.\nrun '/authenticate' to connect.";
+
+/// Why someone can't do something; Displays as the message shown to them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    Frozen,
+    Unauthorized,
+    NoPermission,
+    NothingPlaying,
+    /// They've requested as many songs as their quota allows
+    Quota {
+        used: i64,
+        max_requests: i32,
+        /// Seconds
+        period: i32,
+        /// When the oldest request stops counting
+        resets: Option<DateTime<Utc>>,
+    },
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Frozen => f.write_str("Playback changes are frozen"),
+            Reason::Unauthorized => f.write_str(UNAUTHORIZED),
+            Reason::NoPermission => f.write_str("You don't have permission to run this command"),
+            Reason::NothingPlaying => f.write_str("Nothing Playing; can't modify playback."),
+            Reason::Quota {
+                used,
+                max_requests,
+                period,
+                resets,
+            } => {
+                write!(
+                    f,
+                    "You've used {used} of your {max_requests} songs per {}",
+                    format_period(*period)
+                )?;
+                if let Some(resets) = resets {
+                    write!(f, "; more are allowed <t:{}:R>", resets.timestamp())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Someone running a command or pressing a button, with what's needed to check what they can do
+pub struct Actor {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub roles: Vec<RoleId>,
+    /// A bot owner, the server owner, or has Manage Server
+    pub is_admin: bool,
+}
+
+impl Actor {
+    /// The author of a command
+    pub async fn from_context(ctx: Context<'_>) -> Result<Self, Error> {
+        let guild_id = crate::guild_id(ctx)?;
+        let user_id = ctx.author().id;
+        let (roles, manages_guild) = match ctx.author_member().await {
+            Some(member) => (
+                member.roles.clone(),
+                member.permissions.is_some_and(|v| v.manage_guild()),
+            ),
+            None => (Vec::new(), false),
+        };
+
+        let owners = &ctx.framework().options.owners;
+        Ok(Self {
+            guild_id,
+            user_id,
+            roles,
+            is_admin: is_admin(
+                ctx.serenity_context(),
+                owners,
+                guild_id,
+                user_id,
+                manages_guild,
+            ),
+        })
+    }
+
+    /// Whoever pressed a button
+    pub fn from_component(
+        ctx: &serenity::Context,
+        owners: &HashSet<UserId>,
+        interaction: &ComponentInteraction,
+    ) -> Result<Self, Error> {
+        let guild_id = interaction
+            .guild_id
+            .ok_or("Buttons can only be used in a server")?;
+        let user_id = interaction.user.id;
+        let (roles, manages_guild) = match &interaction.member {
+            Some(member) => (
+                member.roles.clone(),
+                member.permissions.is_some_and(|v| v.manage_guild()),
+            ),
+            None => (Vec::new(), false),
+        };
+
+        Ok(Self {
+            guild_id,
+            user_id,
+            roles,
+            is_admin: is_admin(ctx, owners, guild_id, user_id, manages_guild),
+        })
+    }
+}
+
+fn is_admin(
+    ctx: &serenity::Context,
+    owners: &HashSet<UserId>,
+    guild_id: GuildId,
+    user_id: UserId,
+    manages_guild: bool,
+) -> bool {
+    owners.contains(&user_id)
+        || manages_guild
+        || ctx
+            .cache
+            .guild(guild_id)
+            .is_some_and(|guild| guild.owner_id == user_id)
+}
+
+/// Checks if the actor is an admin, or who connected the server's Spotify
+pub async fn is_owner(data: &Data, actor: &Actor) -> Result<bool, Error> {
+    if actor.is_admin {
+        return Ok(true);
+    }
+
+    let guild = actor.guild_id.get() as i64;
+    let owner = db_get_token_owner(&data.pool, guild).await?;
+    Ok(owner == Some(actor.user_id.get() as i64))
+}
+
+/// Returns the user's highest role, whether given directly or through their Discord roles
+pub async fn user_role(
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
+    roles: &[RoleId],
+) -> Result<Option<Role>, Error> {
+    let roles = roles.iter().map(|v| v.get() as i64).collect::<Vec<_>>();
+    db_get_user_role(
+        &data.pool,
+        guild_id.get() as i64,
+        user_id.get() as i64,
+        &roles,
+    )
+    .await
+}
+
+/// Returns whether the actor can hand out the role; Only owners can give roles at or above their own
+pub async fn can_assign(data: &Data, actor: &Actor, role: &Role) -> Result<bool, Error> {
+    if is_owner(data, actor).await? {
+        return Ok(true);
+    }
+
+    match user_role(data, actor.guild_id, actor.user_id, &actor.roles).await? {
+        Some(own) => Ok(role.level < own.level),
+        None => Ok(false),
+    }
+}
+
+/// Returns whether the actor can change or remove the user; Only owners can change users at or above their own level
+pub async fn can_manage(
+    data: &Data,
+    actor: &Actor,
+    user_id: UserId,
+    roles: &[RoleId],
+) -> Result<bool, Error> {
+    if is_owner(data, actor).await? {
+        return Ok(true);
+    }
+
+    let target = match user_role(data, actor.guild_id, user_id, roles).await? {
+        Some(v) => v,
+        None => return Ok(true),
+    };
+    match user_role(data, actor.guild_id, actor.user_id, &actor.roles).await? {
+        Some(own) => Ok(target.level < own.level),
+        None => Ok(false),
+    }
+}
+
+/// Returns whether the actor's role grants the capability; Owners are always allowed
+pub async fn has_capability(
+    data: &Data,
+    actor: &Actor,
+    capability: Capability,
+) -> Result<bool, Error> {
+    if is_owner(data, actor).await? {
+        return Ok(true);
+    }
+
+    match user_role(data, actor.guild_id, actor.user_id, &actor.roles).await? {
+        Some(role) => Ok(role.can(capability)),
+        None => Ok(false),
+    }
+}
+
+/// Returns why the actor can't change playback, or none if they can; Playback can be stopped
+pub async fn change_denial(
+    data: &Data,
+    actor: &Actor,
+    capability: Capability,
+) -> Result<Option<Reason>, Error> {
+    if data.is_frozen(actor.guild_id).await? {
+        return Ok(Some(Reason::Frozen));
+    }
+    if data.client(actor.guild_id).await.is_none() {
+        return Ok(Some(Reason::Unauthorized));
+    }
+    if !has_capability(data, actor, capability).await? {
+        return Ok(Some(Reason::NoPermission));
+    }
+
+    Ok(None)
+}
+
+/// Like `change_denial`, but something has to be playing
+pub async fn playback_denial(
+    data: &Data,
+    actor: &Actor,
+    capability: Capability,
+) -> Result<Option<Reason>, Error> {
+    if let Some(reason) = change_denial(data, actor, capability).await? {
+        return Ok(Some(reason));
+    }
+
+    let client = match data.client(actor.guild_id).await {
+        Some(v) => v,
+        None => return Ok(Some(Reason::Unauthorized)),
+    };
+    if client
+        .current_playing(None, None::<Vec<_>>)
+        .await?
+        .is_none()
+    {
+        return Ok(Some(Reason::NothingPlaying));
+    }
+
+    Ok(None)
+}
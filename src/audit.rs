@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use poise::serenity_prelude::{GuildId, UserId};
+
+use crate::database::db_add_audit;
+use crate::Error;
+
+/// What was done; Stored by name in `audit_log.action`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum AuditAction {
+    Play,
+    Skip,
+    Previous,
+    #[name = "Pause/Resume"]
+    Pause,
+    Seek,
+    Volume,
+    Shuffle,
+    Repeat,
+    Device,
+    Unqueue,
+    Move,
+    #[name = "Clear Queue"]
+    ClearQueue,
+    Freeze,
+    #[name = "Freeze Schedule"]
+    FreezeSchedule,
+    #[name = "Add User"]
+    AddUser,
+    #[name = "Remove User"]
+    RemoveUser,
+    #[name = "Grant Role"]
+    GrantRole,
+    #[name = "Revoke Role"]
+    RevokeRole,
+    Quota,
+    Settings,
+    Blocklist,
+    Authenticate,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Play => "play",
+            AuditAction::Skip => "skip",
+            AuditAction::Previous => "previous",
+            AuditAction::Pause => "pause",
+            AuditAction::Seek => "seek",
+            AuditAction::Volume => "volume",
+            AuditAction::Shuffle => "shuffle",
+            AuditAction::Repeat => "repeat",
+            AuditAction::Device => "device",
+            AuditAction::Unqueue => "unqueue",
+            AuditAction::Move => "move",
+            AuditAction::ClearQueue => "clear_queue",
+            AuditAction::Freeze => "freeze",
+            AuditAction::FreezeSchedule => "freeze_schedule",
+            AuditAction::AddUser => "add_user",
+            AuditAction::RemoveUser => "remove_user",
+            AuditAction::GrantRole => "grant_role",
+            AuditAction::RevokeRole => "revoke_role",
+            AuditAction::Quota => "quota",
+            AuditAction::Settings => "settings",
+            AuditAction::Blocklist => "blocklist",
+            AuditAction::Authenticate => "authenticate",
+        }
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "play" => Ok(AuditAction::Play),
+            "skip" => Ok(AuditAction::Skip),
+            "previous" => Ok(AuditAction::Previous),
+            "pause" => Ok(AuditAction::Pause),
+            "seek" => Ok(AuditAction::Seek),
+            "volume" => Ok(AuditAction::Volume),
+            "shuffle" => Ok(AuditAction::Shuffle),
+            "repeat" => Ok(AuditAction::Repeat),
+            "device" => Ok(AuditAction::Device),
+            "unqueue" => Ok(AuditAction::Unqueue),
+            "move" => Ok(AuditAction::Move),
+            "clear_queue" => Ok(AuditAction::ClearQueue),
+            "freeze" => Ok(AuditAction::Freeze),
+            "freeze_schedule" => Ok(AuditAction::FreezeSchedule),
+            "add_user" => Ok(AuditAction::AddUser),
+            "remove_user" => Ok(AuditAction::RemoveUser),
+            "grant_role" => Ok(AuditAction::GrantRole),
+            "revoke_role" => Ok(AuditAction::RevokeRole),
+            "quota" => Ok(AuditAction::Quota),
+            "settings" => Ok(AuditAction::Settings),
+            "blocklist" => Ok(AuditAction::Blocklist),
+            "authenticate" => Ok(AuditAction::Authenticate),
+            _ => Err(format!("Unknown audit action '{s}'")),
+        }
+    }
+}
+
+/// Something to write to the audit log
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub action: AuditAction,
+    /// What it was done to, like a song or user
+    pub target: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+impl Event {
+    pub fn new(action: AuditAction) -> Self {
+        Self {
+            action,
+            target: None,
+            old_value: None,
+            new_value: None,
+        }
+    }
+
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// The value before the change, if it's known
+    pub fn from(mut self, old_value: impl Into<String>) -> Self {
+        self.old_value = Some(old_value.into());
+        self
+    }
+
+    pub fn to(mut self, new_value: impl Into<String>) -> Self {
+        self.new_value = Some(new_value.into());
+        self
+    }
+
+    pub fn describe(&self) -> String {
+        describe(
+            self.target.as_deref(),
+            self.old_value.as_deref(),
+            self.new_value.as_deref(),
+        )
+    }
+}
+
+/// Describes a change, like "Song Name: 50% → 80%"; Empty if there's nothing to say
+pub fn describe(target: Option<&str>, old_value: Option<&str>, new_value: Option<&str>) -> String {
+    let change = match (old_value, new_value) {
+        (Some(old), Some(new)) => format!("{old} → {new}"),
+        (Some(old), None) => format!("was {old}"),
+        (None, Some(new)) => new.to_string(),
+        (None, None) => String::new(),
+    };
+
+    match (target, change.is_empty()) {
+        (Some(target), true) => target.to_string(),
+        (Some(target), false) => format!("{target}: {change}"),
+        (None, _) => change,
+    }
+}
+
+/// Writes who did what to the guild's audit log
+pub async fn record(
+    pool: &sqlx::PgPool,
+    guild_id: GuildId,
+    user_id: UserId,
+    event: &Event,
+) -> Result<(), Error> {
+    db_add_audit(
+        pool,
+        guild_id.get() as i64,
+        user_id.get() as i64,
+        event.action.as_str(),
+        event.target.as_deref(),
+        event.old_value.as_deref(),
+        event.new_value.as_deref(),
+    )
+    .await
+}
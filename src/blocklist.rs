@@ -0,0 +1,119 @@
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use poise::serenity_prelude::GuildId;
+use rspotify::{
+    model::{AlbumId, ArtistId, TrackId},
+    prelude::Id,
+};
+
+use crate::database::db_get_blocklist;
+use crate::spotify::{split_link, ItemId, StandardItem};
+use crate::Error;
+
+/// What a blocklist entry matches; Stored by name in `blocklist.kind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    Track,
+    Artist,
+    Album,
+}
+
+impl BlockKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockKind::Track => "track",
+            BlockKind::Artist => "artist",
+            BlockKind::Album => "album",
+        }
+    }
+}
+
+impl FromStr for BlockKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "track" => Ok(BlockKind::Track),
+            "artist" => Ok(BlockKind::Artist),
+            "album" => Ok(BlockKind::Album),
+            _ => Err(format!("Unknown block kind '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for BlockKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses a link to a song, artist or album; Returns the kind and Spotify id
+pub fn parse_target(input: &str) -> Option<(BlockKind, String)> {
+    let (kind, id) = split_link(input)?;
+    let valid = match kind {
+        "track" => TrackId::from_id(id).is_ok(),
+        "artist" => ArtistId::from_id(id).is_ok(),
+        "album" => AlbumId::from_id(id).is_ok(),
+        _ => false,
+    };
+    if !valid {
+        return None;
+    }
+
+    Some((kind.parse().ok()?, id.to_string()))
+}
+
+/// A guild's blocklist, loaded to check items against
+#[derive(Debug)]
+pub struct Blocklist {
+    entries: HashSet<(BlockKind, String)>,
+}
+
+impl Blocklist {
+    pub async fn load(pool: &sqlx::PgPool, guild_id: GuildId) -> Result<Self, Error> {
+        let entries = db_get_blocklist(pool, guild_id.get() as i64)
+            .await?
+            .into_iter()
+            .filter_map(|v| Some((v.kind.parse().ok()?, v.item_id)))
+            .collect();
+
+        Ok(Self { entries })
+    }
+
+    fn contains(&self, kind: BlockKind, id: &str) -> bool {
+        self.entries.contains(&(kind, id.to_string()))
+    }
+
+    /// Returns why the item is blocked, or none if it isn't
+    pub fn reason(&self, item: &StandardItem<'_>) -> Option<String> {
+        if let ItemId::Track(id) = &item.id {
+            if self.contains(BlockKind::Track, id.id()) {
+                return Some("This song is blocked".to_string());
+            }
+        }
+        if item
+            .artist_ids
+            .iter()
+            .any(|v| self.contains(BlockKind::Artist, v))
+        {
+            return Some("An artist of this song is blocked".to_string());
+        }
+        if item
+            .album_id
+            .as_ref()
+            .is_some_and(|v| self.contains(BlockKind::Album, v))
+        {
+            return Some("This album is blocked".to_string());
+        }
+
+        None
+    }
+
+    /// Whether an album, or any of its artists, is blocked
+    pub fn blocks_album(&self, album_id: &str, artist_ids: &[String]) -> bool {
+        self.contains(BlockKind::Album, album_id)
+            || artist_ids
+                .iter()
+                .any(|v| self.contains(BlockKind::Artist, v))
+    }
+}
@@ -0,0 +1,166 @@
+use std::{collections::HashMap, sync::Arc, time::Duration, time::Instant};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use poise::serenity_prelude::{ChannelId, GuildId, Http, UserId};
+use rspotify::{prelude::OAuthClient, AuthCodePkceSpotify};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::audit::{self, AuditAction, Event};
+use crate::log_channel;
+
+/// How long someone has to finish connecting Spotify after /authenticate
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// An /authenticate waiting for Spotify to redirect back
+pub struct PendingAuth {
+    pub guild_id: GuildId,
+    /// Who ran /authenticate; Only their flow can use this state
+    pub user_id: UserId,
+    /// Where to confirm once connected
+    pub channel_id: ChannelId,
+    /// Holds the PKCE verifier the code is exchanged with
+    pub client: AuthCodePkceSpotify,
+    pub started: Instant,
+}
+
+/// Flows waiting for the callback, by their OAuth state
+pub type PendingAuths = Arc<RwLock<HashMap<String, PendingAuth>>>;
+
+/// What the callback server needs from the bot
+#[derive(Clone)]
+pub struct CallbackState {
+    pub pending: PendingAuths,
+    pub spotify: Arc<RwLock<HashMap<GuildId, AuthCodePkceSpotify>>>,
+    pub pool: sqlx::PgPool,
+    pub http: Arc<Http>,
+}
+
+/// Query Spotify redirects with; Either a code or an error
+#[derive(Debug, Deserialize)]
+pub struct CallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Waits for the callback of a flow; Replaces any earlier flow of the same person in the guild
+pub async fn start(pending: &PendingAuths, state: String, auth: PendingAuth) {
+    let mut pending = pending.write().await;
+    pending.retain(|_, v| {
+        v.started.elapsed() < AUTH_TIMEOUT
+            && (v.guild_id, v.user_id) != (auth.guild_id, auth.user_id)
+    });
+    pending.insert(state, auth);
+}
+
+/// Returns the flow the state belongs to; Each state can only be used once, before it expires
+pub async fn take(pending: &PendingAuths, state: &str) -> Option<PendingAuth> {
+    let auth = pending.write().await.remove(state)?;
+    (auth.started.elapsed() < AUTH_TIMEOUT).then_some(auth)
+}
+
+/// Returns the path of the redirect URI, which the callback is served at
+pub fn redirect_path(redirect_uri: &str) -> String {
+    let without_scheme = match redirect_uri.split_once("://") {
+        Some((_, rest)) => rest,
+        None => redirect_uri,
+    };
+    let path = match without_scheme.find('/') {
+        Some(index) => &without_scheme[index..],
+        None => "/",
+    };
+
+    path.split(['?', '#']).next().unwrap_or("/").to_string()
+}
+
+/// Routes the callback at the path of the redirect URI
+pub fn router(redirect_uri: &str, state: CallbackState) -> Router {
+    Router::new()
+        .route(&redirect_path(redirect_uri), get(callback))
+        .with_state(state)
+}
+
+async fn callback(
+    State(state): State<CallbackState>,
+    Query(params): Query<CallbackParams>,
+) -> (StatusCode, &'static str) {
+    let auth = match params.state.as_deref() {
+        Some(v) => take(&state.pending, v).await,
+        None => None,
+    };
+    let auth = match auth {
+        Some(v) => v,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "This link expired or was already used; run /authenticate again",
+            )
+        }
+    };
+
+    match finish(&state, &auth, params).await {
+        Ok(()) => (
+            StatusCode::OK,
+            "Spotify is connected; you can close this tab and go back to Discord",
+        ),
+        Err(err) => {
+            warn!("Failed to authenticate {}: {err}", auth.guild_id);
+            let message = format!("<@{}> Failed to Authenticate:\n{err}", auth.user_id);
+            if let Err(err) = auth.channel_id.say(&state.http, message).await {
+                warn!(
+                    "Failed to tell {} authentication failed: {err}",
+                    auth.guild_id
+                );
+            }
+            (
+                StatusCode::BAD_REQUEST,
+                "Spotify couldn't be connected; check Discord and run /authenticate again",
+            )
+        }
+    }
+}
+
+/// Exchanges the code for a token, then confirms in Discord
+async fn finish(
+    state: &CallbackState,
+    auth: &PendingAuth,
+    params: CallbackParams,
+) -> Result<(), crate::Error> {
+    if let Some(error) = params.error {
+        return Err(format!("Spotify returned '{error}'").into());
+    }
+    let code = params.code.ok_or("Spotify didn't return a code")?;
+
+    auth.client.request_token(&code).await?;
+    info!("Recieved Spotify token for {}", auth.guild_id);
+    state
+        .spotify
+        .write()
+        .await
+        .insert(auth.guild_id, auth.client.clone());
+
+    auth.channel_id
+        .say(
+            &state.http,
+            format!("<@{}> Successfully Authenticated!", auth.user_id),
+        )
+        .await?;
+
+    let event = Event::new(AuditAction::Authenticate).to("Connected");
+    audit::record(&state.pool, auth.guild_id, auth.user_id, &event).await?;
+    log_channel::post(
+        &state.http,
+        &state.pool,
+        auth.guild_id,
+        auth.user_id,
+        &event,
+    )
+    .await
+}
@@ -1,27 +1,90 @@
-use crate::database::{db_add_user, db_get_user_permission, db_remove_user, db_user_exists};
-use crate::spotify::{fetch_queue, fetch_track, StandardItem};
-use crate::{format_delta, is_frozen, spotify, Context, Error};
+use crate::access::{self, change_denial, playback_denial, Actor, Reason};
+use crate::audit::{self, AuditAction, Event};
+use crate::blocklist::{self, BlockKind, Blocklist};
+use crate::callback::{self, PendingAuth};
+use crate::database::{
+    db_add_block, db_add_freeze_schedule, db_add_role_grant, db_add_user, db_get_audit,
+    db_get_blocklist, db_get_freeze_schedules, db_get_history, db_get_log_settings,
+    db_get_role_grants, db_get_roles, db_get_settings, db_get_token_owner, db_remove_block,
+    db_remove_freeze_schedule, db_remove_play, db_remove_role_grant, db_remove_role_quota,
+    db_remove_user, db_remove_user_quota, db_save_log_settings, db_save_settings,
+    db_set_role_quota, db_set_user_quota, db_user_exists, GuildSettings, LogSettings, Quota,
+};
+use crate::log_channel::{self, LogEvent};
+use crate::permissions::{Capability, Role};
+use crate::policy::QueuePolicy;
+use crate::service::{self, Position, QueueOutcome};
+use crate::spotify::{ItemId, SpotifyLink, StandardItem};
+use crate::{
+    format_delta, format_period, freeze, guild_id, nowplaying, panel, parse_delta, spotify,
+    Context, Error,
+};
 use anyhow::Context as _;
+use chrono::{NaiveDate, NaiveTime, TimeDelta, Utc};
+use chrono_tz::Tz;
 use poise::serenity_prelude::{
-    self as serenity, ButtonStyle, Colour, CreateActionRow, CreateButton, CreateEmbed,
-    CreateEmbedAuthor, CreateEmbedFooter, CreateInteractionResponse, Timestamp, UserId,
+    self as serenity, ButtonStyle, ChannelId, Colour, CreateActionRow, CreateButton, CreateEmbed,
+    CreateEmbedAuthor, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseMessage, GuildId, RoleId, Timestamp, UserId,
 };
-use poise::{CreateReply, Modal};
+use poise::{ChoiceParameter, CreateReply};
 use rspotify::model::{
-    CurrentPlaybackContext, IdError, PlayableId, PlayableItem, RepeatState, SearchResult,
-    SearchType, TrackId,
+    AlbumId, ArtistId, CurrentPlaybackContext, PlayableItem, RepeatState, SearchResult, SearchType,
+    TrackId,
 };
-use rspotify::prelude::{BaseClient, OAuthClient};
+use rspotify::prelude::{BaseClient, Id, OAuthClient};
+use rspotify::AuthCodePkceSpotify;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
 use tracing::{debug, info};
 
-/// Modal for authentication
-#[derive(Debug, Modal)]
-#[name = "Spotify Authentication"]
-struct SpotifyAuthenticationModal {
-    #[name = "Paste the code that you recieved here"]
-    #[min_length = 64]
-    #[max_length = 512]
-    code: String,
+/// Most plays shown by /history
+const HISTORY_LIMIT: i64 = 100;
+/// Plays shown on each page of /history
+const HISTORY_PAGE_SIZE: usize = 10;
+/// Most entries shown by /audit
+const AUDIT_LIMIT: i64 = 200;
+/// Entries shown on each page of /audit
+const AUDIT_PAGE_SIZE: usize = 15;
+/// Entries shown on each page of /blocklist
+const BLOCKLIST_PAGE_SIZE: usize = 20;
+/// Songs shown on each page of /queue
+const QUEUE_PAGE_SIZE: usize = 20;
+/// Characters in the progress bar of /current and /nowplaying
+const PROGRESS_BAR_LENGTH: i64 = 20;
+/// How long /play waits for the user to stop typing before searching
+const TYPING_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What /play searches for
+#[derive(Debug, Clone, Copy, Default, poise::ChoiceParameter)]
+pub enum SearchKind {
+    #[default]
+    #[name = "Song"]
+    Track,
+    Episode,
+    #[name = "Podcast"]
+    Show,
+    Album,
+}
+
+impl SearchKind {
+    /// Used in the prompt to choose a result
+    fn prompt(&self) -> &'static str {
+        match self {
+            SearchKind::Track => "A Song",
+            SearchKind::Episode => "An Episode",
+            SearchKind::Show => "A Podcast",
+            SearchKind::Album => "An Album",
+        }
+    }
+}
+
+/// What /repeat repeats
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum RepeatMode {
+    Off,
+    Track,
+    Context,
 }
 
 /*
@@ -31,17 +94,76 @@ Playback Commands
 */
 
 /// Check the current playback
-#[poise::command(slash_command, user_cooldown = 10, category = "Playback")]
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
 pub async fn current(ctx: Context<'_>) -> Result<(), Error> {
     run_current(ctx).await
 }
 
+/// Post the current playback and keep it updated
+#[poise::command(slash_command, guild_only, user_cooldown = 30, category = "Playback")]
+pub async fn nowplaying(ctx: Context<'_>) -> Result<(), Error> {
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    let playback = client.current_playback(None, None::<Vec<_>>).await?;
+    let embed = match playback.as_ref().and_then(|v| Some((v, v.item.clone()?))) {
+        Some((playback, item)) => current_playback(playback, item, CreateEmbed::new()).await,
+        None => current_no_playback(CreateEmbed::new()).await,
+    };
+
+    let message = ctx
+        .send(CreateReply::default().embed(embed))
+        .await?
+        .message()
+        .await?
+        .id;
+
+    // Replaces the channel's previous live message, which stops updating
+    let (guild_id, channel_id) = (guild_id(ctx)?, ctx.channel_id());
+    let live = ctx.data().live_messages.clone();
+    live.write().await.insert(channel_id, message);
+    tokio::spawn(nowplaying::run(
+        ctx.serenity_context().http.clone(),
+        ctx.data().spotify.clone(),
+        live,
+        guild_id,
+        channel_id,
+        message,
+    ));
+
+    Ok(())
+}
+
+/// Post buttons for controlling playback
+#[poise::command(slash_command, guild_only, user_cooldown = 30, category = "Playback")]
+pub async fn panel(ctx: Context<'_>) -> Result<(), Error> {
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    let embed = panel::embed(&client).await?;
+    ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .components(panel::components()),
+    )
+    .await?;
+    Ok(())
+}
+
 /// Check the queue
-#[poise::command(slash_command, user_cooldown = 10, category = "Playback")]
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
 pub async fn queue(ctx: Context<'_>) -> Result<(), Error> {
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
+    let client = match spotify::client(ctx).await {
         Some(v) => v,
         None => {
             error_unauthorized(ctx).await?;
@@ -49,114 +171,164 @@ pub async fn queue(ctx: Context<'_>) -> Result<(), Error> {
         }
     };
 
-    // The current playing song
-    let current = match client.current_playing(None, None::<Vec<_>>).await? {
-        Some(v) => StandardItem::parse(v.item.unwrap()),
+    let guild_id = guild_id(ctx)?;
+    let view = match service::queue_view(ctx.data(), guild_id, &client).await? {
+        Some(v) => v,
         None => {
             let embed = current_no_playback(CreateEmbed::default()).await;
             ctx.send(CreateReply::default().embed(embed)).await?;
             return Ok(());
         }
     };
-    drop(lock);
 
-    // The queue
-    let data = fetch_queue(ctx).await?;
-    let mut queue = Vec::new();
-    for (index, value) in data.into_iter().enumerate() {
-        // Limit queue to 5 results
-        if index == 5 {
-            break;
+    let mut entries = Vec::new();
+    for (index, (item, requester)) in view.upcoming.iter().enumerate() {
+        let mut entry = format_queue_entry(index + 1, item, *requester);
+        if index == 0 {
+            entry = format!("**Up Next**\n{entry}");
         }
+        entries.push(entry);
+    }
 
-        queue.push(format!(
-            "**[{}]({})**\n{}",
-            value.name,
-            value.url,
-            value.artists.join(", "),
-        ));
+    // Requests the bot hasn't sent to Spotify yet
+    for (index, request) in view.waiting.iter().enumerate() {
+        // Numbered by position in the bot's queue, as used by /unqueue and /move
+        let mut entry = format_queue_entry(index + 1, &request.item, Some(request.requester));
+        if index == 0 {
+            entry = format!("**Waiting For Their Turn**\n{entry}");
+        }
+        entries.push(entry);
     }
 
-    if queue.len() == 0 {
+    if entries.is_empty() {
         ctx.say("Nothings in the queue.").await?;
         return Ok(());
     }
 
-    let embed = CreateEmbed::new()
-        .colour(Colour::DARK_GREEN)
-        .author(
-            CreateEmbedAuthor::new(current.get_title())
-            .url(current.url)
-            .icon_url("https://storage.googleapis.com/pr-newsroom-wp/1/2023/05/Spotify_Primary_Logo_RGB_Green.png"),
-        )
-        .title("Current Queue")
-        .description("The next five songs that are in the queue.")
-        .thumbnail(current.image)
-        .timestamp(Timestamp::now())
-        .footer(CreateEmbedFooter::new("Delegatify"))
-        .description(format!("{}\n**...**", queue.join("\n\n")));
-
-    ctx.send(CreateReply::default().embed(embed)).await?;
-    Ok(())
+    let pages = entries
+        .chunks(QUEUE_PAGE_SIZE)
+        .map(|chunk| {
+            CreateEmbed::new()
+                .colour(Colour::DARK_GREEN)
+                .author(
+                    CreateEmbedAuthor::new(view.current.get_title())
+                    .url(view.current.url.clone())
+                    .icon_url("https://storage.googleapis.com/pr-newsroom-wp/1/2023/05/Spotify_Primary_Logo_RGB_Green.png"),
+                )
+                .title("Current Queue")
+                .thumbnail(view.current.image.clone())
+                .field("Songs", entries.len().to_string(), true)
+                .field("Remaining", format_delta(view.remaining), true)
+                .timestamp(Timestamp::now())
+                .description(chunk.join("\n"))
+        })
+        .collect();
+
+    paginate(ctx, pages).await
 }
 
 /// Add a song to the queue
 #[poise::command(
     slash_command,
+    guild_only,
     user_cooldown = 60,
     global_cooldown = 30,
     category = "Playback"
 )]
 pub async fn play(
     ctx: Context<'_>,
-    #[description = "Either the URL or search query"]
+    #[description = "A Spotify link (song, episode, album or playlist) or search query"]
     #[max_length = 512]
+    #[autocomplete = "autocomplete_play"]
     input: String,
+    #[description = "What to search for; Defaults to songs"]
+    #[rename = "type"]
+    kind: Option<SearchKind>,
 ) -> Result<(), Error> {
-    if !allow_playback(ctx, 1).await? {
-        return Ok(());
-    }
-
-    let id = if input.starts_with("https") && input.contains("track") && input.contains("spotify") {
-        play_url(&input).await?
-    } else {
-        play_search(ctx, input).await?
-    };
-
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
+    let actor = Actor::from_context(ctx).await?;
+    let link = match SpotifyLink::parse(&input) {
         Some(v) => v,
-        None => {
-            error_unauthorized(ctx).await?;
+        None if input.starts_with("http") || input.starts_with("spotify:") => {
+            ctx.say("Only Spotify songs, episodes, podcasts, albums and playlists can be queued")
+                .await?;
             return Ok(());
         }
+        None => play_search(ctx, input, kind.unwrap_or_default()).await?,
     };
 
-    client
-        .add_item_to_queue(PlayableId::Track(id.clone()), None)
-        .await?;
-    drop(lock);
+    let queued = match service::queue_link(ctx.data(), &actor, &link).await? {
+        QueueOutcome::Added(v) => v,
+        QueueOutcome::Denied(reason) => {
+            ctx.say(reason.to_string()).await?;
+            return Ok(());
+        }
+        QueueOutcome::Empty => {
+            ctx.say(format!(
+                "That {} has nothing that can be queued",
+                link.kind().to_lowercase()
+            ))
+            .await?;
+            return Ok(());
+        }
+        QueueOutcome::Blocked { item, reason } => {
+            let embed = CreateEmbed::new()
+                .colour(Colour::DARK_RED)
+                .author(CreateEmbedAuthor::new(format!(
+                    "Can't Queue {}",
+                    link.kind()
+                )))
+                .title(item.get_title())
+                .thumbnail(item.image.clone())
+                .field("Reason", reason, false)
+                .timestamp(Timestamp::now());
+            ctx.send(CreateReply::default().embed(embed)).await?;
+            return Ok(());
+        }
+    };
 
-    let track = fetch_track(ctx, id).await?;
-    let title = track.get_title();
-    let embed = CreateEmbed::new()
+    let first = &queued.items[0];
+    let title = match &queued.name {
+        Some(name) => name.clone(),
+        None => first.get_title(),
+    };
+    let position = match queued.position {
+        Position::PlayingNow => "Playing now".to_string(),
+        Position::Waiting(v) => v.to_string(),
+    };
+    let length: TimeDelta = queued.items.iter().map(|v| v.duration).sum();
+    let mut embed = CreateEmbed::new()
         .colour(Colour::DARK_GREEN)
-        .author(CreateEmbedAuthor::new("Added Song To Queue"))
+        .author(CreateEmbedAuthor::new(format!(
+            "Added {} To Queue",
+            link.kind()
+        )))
         .title(title.clone())
-        .thumbnail(track.image)
-        .field(
-            "Length",
-            format!("{}s", format_delta(track.duration)),
-            false,
-        )
-        .timestamp(Timestamp::now())
-        .footer(
-            CreateEmbedFooter::new(format!("Requested by {}", ctx.author().name))
-                .icon_url(ctx.author().avatar_url().unwrap_or_default()),
-        );
+        .thumbnail(first.image.clone())
+        .field("Length", format!("{}s", format_delta(length)), true)
+        .field("Position", position, true);
+    match (&queued.name, first.popularity) {
+        (Some(_), _) => embed = embed.field("Songs", queued.items.len().to_string(), true),
+        (None, Some(popularity)) => {
+            embed = embed.field("Popularity", format!("{popularity}/100"), true)
+        }
+        (None, None) => (),
+    }
+    if let Some((_, reason)) = queued.skipped.first() {
+        embed = embed.field(format!("Skipped {}", queued.skipped.len()), reason, false);
+    }
+    let embed = embed.timestamp(Timestamp::now()).footer(
+        CreateEmbedFooter::new(format!("Requested by {}", ctx.author().name))
+            .icon_url(ctx.author().avatar_url().unwrap_or_default()),
+    );
 
     ctx.send(CreateReply::default().embed(embed)).await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Play)
+            .target(title.clone())
+            .to(format!("{} songs", queued.items.len())),
+    )
+    .await?;
 
     // Just some logging
     info!(
@@ -167,21 +339,198 @@ pub async fn play(
     Ok(())
 }
 
+/// Remove a song you requested that hasn't been sent to Spotify yet
+#[poise::command(slash_command, guild_only, user_cooldown = 5, category = "Playback")]
+pub async fn unqueue(
+    ctx: Context<'_>,
+    #[description = "Position under \"Waiting For Their Turn\" in /queue"]
+    #[min = 1]
+    position: usize,
+) -> Result<(), Error> {
+    if !is_allowed(ctx, Capability::Queue).await? {
+        return Ok(());
+    }
+    let guild_id = guild_id(ctx)?;
+
+    // Moderators can remove anyone's requests
+    let expected = match ctx.data().requests.read().await.get(&guild_id) {
+        Some(requests) => requests
+            .get(position - 1)
+            .map(|v| (v.requester, v.item.uri())),
+        None => None,
+    };
+    let (requester, uri) = match expected {
+        Some(v) => v,
+        None => {
+            ctx.say(format!("Nothing is waiting at position {position}"))
+                .await?;
+            return Ok(());
+        }
+    };
+    if requester != ctx.author().id && !has_capability(ctx, Capability::ManageQueue).await? {
+        ctx.say("You can only remove songs you requested").await?;
+        return Ok(());
+    }
+
+    // Checked again as the queue may have changed while the lock wasn't held
+    let request = match ctx.data().requests.write().await.get_mut(&guild_id) {
+        Some(requests)
+            if requests
+                .get(position - 1)
+                .is_some_and(|v| v.requester == requester && v.item.uri() == uri) =>
+        {
+            requests.remove(position - 1)
+        }
+        _ => None,
+    };
+    let request = match request {
+        Some(v) => v,
+        None => {
+            ctx.say("The queue changed; check /queue and try again")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let guild = guild_to_id(guild_id).await;
+    db_remove_play(
+        &ctx.data().pool,
+        guild,
+        &request.item,
+        user_to_id(request.requester).await,
+    )
+    .await?;
+    ctx.say(format!("Removed {}", request.item.get_title()))
+        .await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Unqueue)
+            .target(request.item.get_title())
+            .from(position.to_string()),
+    )
+    .await?;
+
+    // Just some logging
+    info!(
+        "{} removed {} from the queue",
+        user_to_id(ctx.author().id).await,
+        request.item.get_title()
+    );
+    Ok(())
+}
+
+/// Move a song that hasn't been sent to Spotify yet
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "move",
+    check = "can_manage_queue",
+    category = "Playback"
+)]
+pub async fn move_request(
+    ctx: Context<'_>,
+    #[description = "Position under \"Waiting For Their Turn\" in /queue"]
+    #[min = 1]
+    from: usize,
+    #[description = "Position to move it to"]
+    #[min = 1]
+    to: usize,
+) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    let moved = match ctx.data().requests.write().await.get_mut(&guild_id) {
+        Some(requests) => match requests.move_to(from - 1, to - 1) {
+            true => requests.get(to - 1).map(|v| v.item.get_title()),
+            false => None,
+        },
+        None => None,
+    };
+
+    match moved {
+        Some(title) => {
+            ctx.say(format!("Moved {title} to position {to}")).await?;
+            audit(
+                ctx,
+                Event::new(AuditAction::Move)
+                    .target(title.clone())
+                    .from(from.to_string())
+                    .to(to.to_string()),
+            )
+            .await?;
+            info!(
+                "{} moved {} to position {}",
+                user_to_id(ctx.author().id).await,
+                title,
+                to
+            );
+        }
+        None => {
+            ctx.say("Both positions have to be under \"Waiting For Their Turn\" in /queue")
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove every song that hasn't been sent to Spotify yet; Only your own, unless a moderator
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "clearqueue",
+    user_cooldown = 10,
+    category = "Playback"
+)]
+pub async fn clear_queue(ctx: Context<'_>) -> Result<(), Error> {
+    if !is_allowed(ctx, Capability::Queue).await? {
+        return Ok(());
+    }
+    let guild_id = guild_id(ctx)?;
+    let everyone = has_capability(ctx, Capability::ManageQueue).await?;
+
+    let author = ctx.author().id;
+    let removed = match ctx.data().requests.write().await.get_mut(&guild_id) {
+        Some(requests) => requests.remove_where(|v| everyone || v.requester == author),
+        None => Vec::new(),
+    };
+
+    let guild = guild_to_id(guild_id).await;
+    for request in &removed {
+        let requester = user_to_id(request.requester).await;
+        db_remove_play(&ctx.data().pool, guild, &request.item, requester).await?;
+    }
+
+    ctx.say(format!("Removed {} songs from the queue", removed.len()))
+        .await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::ClearQueue)
+            .target(if everyone { "Everyone" } else { "Own requests" })
+            .from(format!("{} songs", removed.len())),
+    )
+    .await?;
+
+    // Just some logging
+    info!(
+        "{} cleared {} songs from the queue",
+        user_to_id(author).await,
+        removed.len()
+    );
+    Ok(())
+}
+
 /// Play the previous track
 #[poise::command(
     slash_command,
+    guild_only,
     user_cooldown = 60,
     global_cooldown = 30,
     category = "Playback"
 )]
 pub async fn previous(ctx: Context<'_>) -> Result<(), Error> {
-    if !allow_playback(ctx, 1).await? {
+    if !allow_playback(ctx, Capability::Previous).await? {
         return Ok(());
     }
 
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
+    let client = match spotify::client(ctx).await {
         Some(v) => v,
         None => {
             error_unauthorized(ctx).await?;
@@ -189,8 +538,8 @@ pub async fn previous(ctx: Context<'_>) -> Result<(), Error> {
         }
     };
 
-    client.previous_track(None).await?;
-    drop(lock);
+    let skipped = service::previous_track(ctx.data(), guild_id(ctx)?, &client).await?;
+    audit(ctx, skip_event(AuditAction::Previous, skipped)).await?;
 
     run_current(ctx).await?;
 
@@ -205,18 +554,26 @@ pub async fn previous(ctx: Context<'_>) -> Result<(), Error> {
 /// Play the next track
 #[poise::command(
     slash_command,
+    guild_only,
     user_cooldown = 60,
     global_cooldown = 30,
     category = "Playback"
 )]
 pub async fn next(ctx: Context<'_>) -> Result<(), Error> {
-    if !allow_playback(ctx, 1).await? {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let settings = db_get_settings(&ctx.data().pool, guild).await?;
+
+    // When voting, anyone who can queue songs can start a vote
+    let capability = if settings.vote_skip {
+        Capability::Queue
+    } else {
+        Capability::Skip
+    };
+    if !allow_playback(ctx, capability).await? {
         return Ok(());
     }
 
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
+    let client = match spotify::client(ctx).await {
         Some(v) => v,
         None => {
             error_unauthorized(ctx).await?;
@@ -224,92 +581,1552 @@ pub async fn next(ctx: Context<'_>) -> Result<(), Error> {
         }
     };
 
-    client.next_track(None).await?;
-    drop(lock);
+    if settings.vote_skip && !has_capability(ctx, Capability::ForceSkip).await? {
+        return run_vote_skip(ctx, &client, &settings).await;
+    }
+
+    let skipped = service::next_track(ctx.data(), guild_id(ctx)?, &client).await?;
+    audit(ctx, skip_event(AuditAction::Skip, skipped)).await?;
 
     run_current(ctx).await?;
 
     // Just some logging
     info!(
-        "{} skipped to the previous song",
+        "{} skipped to the next song",
         user_to_id(ctx.author().id).await
     );
     Ok(())
 }
 
-/*
+/// Pause playback
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn pause(ctx: Context<'_>) -> Result<(), Error> {
+    if !allow_playback(ctx, Capability::Pause).await? {
+        return Ok(());
+    }
 
-Utilities Commands
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
 
-*/
+    client.pause_playback(None).await?;
+    ctx.say("Paused playback").await?;
+    audit(ctx, Event::new(AuditAction::Pause).to("Paused")).await?;
 
-/// Switch the state of freeze
-#[poise::command(slash_command, owners_only, category = "Utilities")]
-pub async fn freeze(ctx: Context<'_>) -> Result<(), Error> {
-    let mut v = ctx.data().freeze.write().await;
+    // Just some logging
+    info!("{} paused playback", user_to_id(ctx.author().id).await);
+    Ok(())
+}
 
-    if *v {
-        *v = false;
-        ctx.say("Disabled Freeze").await?;
-    } else {
-        *v = true;
-        ctx.say("Enabled Freeze").await?;
+/// Resume playback
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn resume(ctx: Context<'_>) -> Result<(), Error> {
+    if !allow_playback(ctx, Capability::Pause).await? {
+        return Ok(());
     }
 
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    client.resume_playback(None, None).await?;
+    ctx.say("Resumed playback").await?;
+    audit(ctx, Event::new(AuditAction::Pause).to("Playing")).await?;
+
+    // Just some logging
+    info!("{} resumed playback", user_to_id(ctx.author().id).await);
     Ok(())
 }
 
-/// Allow a user with specific permissions
-#[poise::command(slash_command, owners_only, category = "Utilities")]
-pub async fn add_user(
+/// Jump to a time in the current song
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn seek(
     ctx: Context<'_>,
-    #[description = "Person to add"] user: serenity::User,
-    #[description = "Permission level to set for user; default to basic (1)"] level: Option<i16>,
+    #[description = "Time to jump to, like 1:30"]
+    #[max_length = 16]
+    time: String,
 ) -> Result<(), Error> {
-    let id = user_to_id(user.clone().id).await;
+    let position = match parse_delta(&time) {
+        Some(v) => v,
+        None => {
+            ctx.say("Times look like 1:30 (minutes:seconds)").await?;
+            return Ok(());
+        }
+    };
+    if !allow_playback(ctx, Capability::Seek).await? {
+        return Ok(());
+    }
+
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    let item = match client.current_playing(None, None::<Vec<_>>).await? {
+        Some(v) => match v.item {
+            Some(item) => StandardItem::parse(item),
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+    if position >= item.duration {
+        ctx.say(format!(
+            "{} is only {} long",
+            item.name,
+            format_delta(item.duration)
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    client.seek_track(position, None).await?;
+    ctx.say(format!("Jumped to {}", format_delta(position)))
+        .await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Seek)
+            .target(item.get_title())
+            .to(format_delta(position)),
+    )
+    .await?;
+
+    // Just some logging
+    info!(
+        "{} jumped to {} in {}",
+        user_to_id(ctx.author().id).await,
+        format_delta(position),
+        item.get_title()
+    );
+    Ok(())
+}
+
+/// Change the volume
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn volume(
+    ctx: Context<'_>,
+    #[description = "Volume as a percentage"]
+    #[min = 0]
+    #[max = 100]
+    percent: u8,
+) -> Result<(), Error> {
+    if !allow_playback(ctx, Capability::Volume).await? {
+        return Ok(());
+    }
+
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    let old = client
+        .current_playback(None, None::<Vec<_>>)
+        .await?
+        .and_then(|v| v.device.volume_percent);
+    client.volume(percent, None).await?;
+    ctx.say(format!("Volume set to {percent}%")).await?;
+    let mut event = Event::new(AuditAction::Volume).to(format!("{percent}%"));
+    if let Some(old) = old {
+        event = event.from(format!("{old}%"));
+    }
+    audit(ctx, event).await?;
+
+    // Just some logging
+    info!(
+        "{} set the volume to {percent}%",
+        user_to_id(ctx.author().id).await
+    );
+    Ok(())
+}
+
+/// Turn shuffle on or off
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn shuffle(
+    ctx: Context<'_>,
+    #[description = "Whether to shuffle; Switches it if left out"] enabled: Option<bool>,
+) -> Result<(), Error> {
+    if !allow_playback(ctx, Capability::Shuffle).await? {
+        return Ok(());
+    }
+
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    let enabled = match enabled {
+        Some(v) => v,
+        None => match client.current_playback(None, None::<Vec<_>>).await? {
+            Some(playback) => !playback.shuffle_state,
+            None => return Ok(()),
+        },
+    };
+    client.shuffle(enabled, None).await?;
+    ctx.say(if enabled {
+        "Shuffle is on"
+    } else {
+        "Shuffle is off"
+    })
+    .await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Shuffle).to(if enabled { "On" } else { "Off" }),
+    )
+    .await?;
+
+    // Just some logging
+    info!(
+        "{} turned shuffle {}",
+        user_to_id(ctx.author().id).await,
+        if enabled { "on" } else { "off" }
+    );
+    Ok(())
+}
+
+/// Choose what repeats
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn repeat(
+    ctx: Context<'_>,
+    #[description = "Repeat nothing, the song, or the album or playlist"] mode: RepeatMode,
+) -> Result<(), Error> {
+    if !allow_playback(ctx, Capability::Repeat).await? {
+        return Ok(());
+    }
+
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    let state = match mode {
+        RepeatMode::Off => RepeatState::Off,
+        RepeatMode::Track => RepeatState::Track,
+        RepeatMode::Context => RepeatState::Context,
+    };
+    client.repeat(state, None).await?;
+    ctx.say(format!("Repeat set to {}", mode.name())).await?;
+    audit(ctx, Event::new(AuditAction::Repeat).to(mode.name())).await?;
+
+    // Just some logging
+    info!(
+        "{} set repeat to {}",
+        user_to_id(ctx.author().id).await,
+        mode.name()
+    );
+    Ok(())
+}
+
+/// List the Spotify devices playback can move to
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn devices(ctx: Context<'_>) -> Result<(), Error> {
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    let devices = client.device().await?;
+    if devices.is_empty() {
+        ctx.say("No devices are available; open Spotify on one")
+            .await?;
+        return Ok(());
+    }
+
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let preferred = db_get_settings(&ctx.data().pool, guild)
+        .await?
+        .preferred_device;
+    let entries = devices
+        .iter()
+        .map(|device| {
+            let mut entry = format!("**{}**\n{:?}", device.name, device._type);
+            if device.is_active {
+                entry.push_str(" · Playing");
+            }
+            if preferred
+                .as_ref()
+                .is_some_and(|v| v.eq_ignore_ascii_case(&device.name))
+            {
+                entry.push_str(" · Preferred");
+            }
+            entry
+        })
+        .collect::<Vec<_>>();
+
+    let embed = CreateEmbed::new()
+        .colour(Colour::DARK_GREEN)
+        .title("Devices")
+        .description(entries.join("\n\n"))
+        .timestamp(Timestamp::now())
+        .footer(CreateEmbedFooter::new("Move playback with /device"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Move playback to another device; /play starts on it when nothing is playing
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn device(
+    ctx: Context<'_>,
+    #[description = "Name of the device"]
+    #[max_length = 128]
+    #[autocomplete = "autocomplete_device"]
+    name: String,
+) -> Result<(), Error> {
+    if !allow_change(ctx, Capability::Device).await? {
+        return Ok(());
+    }
+
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    let device = client
+        .device()
+        .await?
+        .into_iter()
+        .find(|v| v.name.eq_ignore_ascii_case(&name));
+    let (id, name) = match device.and_then(|v| Some((v.id?, v.name))) {
+        Some(v) => v,
+        None => {
+            ctx.say(format!("No device is called {name}; see /devices"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    client.transfer_playback(&id, None).await?;
+
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let mut settings = db_get_settings(&ctx.data().pool, guild).await?;
+    let old = settings.preferred_device.replace(name.clone());
+    db_save_settings(&ctx.data().pool, guild, &settings).await?;
+
+    ctx.say(format!("Playing on {name}")).await?;
+    let mut event = Event::new(AuditAction::Device).to(name.clone());
+    if let Some(old) = old {
+        event = event.from(old);
+    }
+    audit(ctx, event).await?;
+
+    // Just some logging
+    info!(
+        "{} moved playback to {}",
+        user_to_id(ctx.author().id).await,
+        name
+    );
+    Ok(())
+}
+
+/// Check what was played recently
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Playback")]
+pub async fn history(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let plays = db_get_history(&ctx.data().pool, guild, HISTORY_LIMIT).await?;
+
+    if plays.is_empty() {
+        ctx.say("Nothing has been played yet.").await?;
+        return Ok(());
+    }
+
+    let pages = plays
+        .chunks(HISTORY_PAGE_SIZE)
+        .map(|chunk| {
+            let entries = chunk
+                .iter()
+                .map(|play| {
+                    let requester = match play.requester {
+                        Some(v) => format!("<@{v}>"),
+                        None => "Spotify".to_string(),
+                    };
+                    let ended = match play.ended.as_deref() {
+                        Some("skipped") => " · Skipped",
+                        Some(_) => "",
+                        None => " · Playing",
+                    };
+                    format!(
+                        "**[{}]({})**\n{}\n{} · <t:{}:R>{}",
+                        play.name,
+                        play.url,
+                        play.artists.join(", "),
+                        requester,
+                        play.started_at.timestamp(),
+                        ended,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            CreateEmbed::new()
+                .colour(Colour::DARK_GREEN)
+                .title("History")
+                .description(entries.join("\n\n"))
+                .timestamp(Timestamp::now())
+        })
+        .collect();
+
+    paginate(ctx, pages).await
+}
+
+/*
+
+Utilities Commands
+
+*/
+
+/// Check how many more songs you can request
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Utilities")]
+pub async fn quota(
+    ctx: Context<'_>,
+    #[description = "Person to check; default to yourself"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let user = user.unwrap_or_else(|| ctx.author().clone());
+
+    let roles = member_roles(ctx, user.id).await?;
+    let usage = match service::quota_usage(ctx.data(), guild_id(ctx)?, user.id, &roles).await? {
+        Some(v) => v,
+        None => {
+            ctx.say(format!("{} can request unlimited songs", user.name))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let remaining = (usage.quota.max_requests as i64 - usage.used).max(0);
+    let mut embed = CreateEmbed::new()
+        .colour(Colour::BLUE)
+        .author(CreateEmbedAuthor::new(&user.name).icon_url(user.avatar_url().unwrap_or_default()))
+        .title("Quota")
+        .field(
+            "Allowed",
+            format!(
+                "{} songs per {}",
+                usage.quota.max_requests,
+                format_period(usage.quota.period)
+            ),
+            true,
+        )
+        .field("Remaining", remaining.to_string(), true)
+        .timestamp(Timestamp::now())
+        .footer(CreateEmbedFooter::new("Delegatify"));
+    if let Some(resets) = usage.resets {
+        embed = embed.field(
+            "Next Song Freed",
+            format!("<t:{}:R>", resets.timestamp()),
+            true,
+        );
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Limit how many songs a role or user can request
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_users",
+    category = "Utilities"
+)]
+pub async fn set_quota(
+    ctx: Context<'_>,
+    #[description = "Songs allowed each period"]
+    #[min = 1]
+    songs: i32,
+    #[description = "Length of the period in minutes"]
+    #[min = 1]
+    #[max = 10080]
+    minutes: i32,
+    #[description = "Role to limit"]
+    #[autocomplete = "autocomplete_role"]
+    role: Option<String>,
+    #[description = "Person to limit; overrides their role's quota"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let quota = Quota {
+        max_requests: songs,
+        period: minutes * 60,
+    };
+    let limit = format!("{songs} songs per {}", format_period(quota.period));
+
+    match (role, user) {
+        (Some(role), None) => {
+            let roles = db_get_roles(&ctx.data().pool).await?;
+            if !roles.iter().any(|v| v.name == role) {
+                ctx.say(format!("There is no role named '{role}'")).await?;
+                return Ok(());
+            }
+
+            db_set_role_quota(&ctx.data().pool, guild, &role, &quota).await?;
+            ctx.say(format!("Limited {role} to {limit}")).await?;
+            audit(ctx, Event::new(AuditAction::Quota).target(role).to(limit)).await?;
+        }
+        (None, Some(user)) => {
+            let id = user_to_id(user.id).await;
+            db_set_user_quota(&ctx.data().pool, guild, id, &quota).await?;
+            ctx.say(format!("Limited {} to {limit}", user.name)).await?;
+            audit(
+                ctx,
+                Event::new(AuditAction::Quota)
+                    .target(format!("<@{}>", user.id))
+                    .to(limit),
+            )
+            .await?;
+        }
+        _ => {
+            ctx.say("Choose either a role or a user").await?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove the limit on how many songs a role or user can request
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_users",
+    category = "Utilities"
+)]
+pub async fn remove_quota(
+    ctx: Context<'_>,
+    #[description = "Role to stop limiting"]
+    #[autocomplete = "autocomplete_role"]
+    role: Option<String>,
+    #[description = "Person to stop limiting"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+
+    let (removed, target) = match (role, user) {
+        (Some(role), None) => (
+            db_remove_role_quota(&ctx.data().pool, guild, &role).await?,
+            role,
+        ),
+        (None, Some(user)) => {
+            let id = user_to_id(user.id).await;
+            (
+                db_remove_user_quota(&ctx.data().pool, guild, id).await?,
+                format!("<@{}>", user.id),
+            )
+        }
+        _ => {
+            ctx.say("Choose either a role or a user").await?;
+            return Ok(());
+        }
+    };
+
+    if removed {
+        ctx.say("Successfully removed quota").await?;
+        audit(
+            ctx,
+            Event::new(AuditAction::Quota).target(target).to("No limit"),
+        )
+        .await?;
+    } else {
+        ctx.say("There was no quota to remove").await?;
+    }
+    Ok(())
+}
+
+/// Configure whether /next starts a vote to skip
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_settings",
+    category = "Utilities"
+)]
+pub async fn vote_settings(
+    ctx: Context<'_>,
+    #[description = "Whether /next starts a vote instead of skipping"] enabled: bool,
+    #[description = "Votes needed to skip"]
+    #[min = 1]
+    #[max = 50]
+    threshold: Option<i16>,
+    #[description = "Percentage of the owner's voice channel needed; 0 to use the threshold"]
+    #[min = 0]
+    #[max = 100]
+    percentage: Option<i16>,
+    #[description = "Seconds a vote stays open"]
+    #[min = 10]
+    #[max = 600]
+    timeout: Option<i32>,
+    #[description = "Only count votes from the owner's voice channel"] voice_only: Option<bool>,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let mut settings = db_get_settings(&ctx.data().pool, guild).await?;
+    let old = vote_summary(&settings);
+
+    settings.vote_skip = enabled;
+    if let Some(v) = threshold {
+        settings.vote_threshold = v;
+    }
+    if let Some(v) = percentage {
+        settings.vote_percentage = (v > 0).then_some(v);
+    }
+    if let Some(v) = timeout {
+        settings.vote_timeout = v;
+    }
+    if let Some(v) = voice_only {
+        settings.vote_voice_only = v;
+    }
+    db_save_settings(&ctx.data().pool, guild, &settings).await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Settings)
+            .target("Vote to skip")
+            .from(old)
+            .to(vote_summary(&settings)),
+    )
+    .await?;
+
+    let required = match settings.vote_percentage {
+        Some(v) => format!("{v}% of the owner's voice channel"),
+        None => format!("{} votes", settings.vote_threshold),
+    };
+    let embed = CreateEmbed::new()
+        .colour(Colour::BLUE)
+        .title("Vote To Skip")
+        .field("Enabled", if enabled { "Yes" } else { "No" }, true)
+        .field("Required", required, true)
+        .field("Timeout", format!("{}s", settings.vote_timeout), true)
+        .field(
+            "Voice Channel Only",
+            if settings.vote_voice_only {
+                "Yes"
+            } else {
+                "No"
+            },
+            true,
+        )
+        .timestamp(Timestamp::now())
+        .footer(CreateEmbedFooter::new("Delegatify"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Configure how songs are queued, and which songs can be
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_settings",
+    category = "Utilities"
+)]
+pub async fn queue_settings(
+    ctx: Context<'_>,
+    #[description = "Most songs queued from one album or playlist"]
+    #[min = 1]
+    #[max = 100]
+    collection_limit: Option<i16>,
+    #[description = "Whether explicit songs can be queued"] allow_explicit: Option<bool>,
+    #[description = "Longest song that can be queued, in minutes; 0 for no limit"]
+    #[min = 0]
+    #[max = 600]
+    max_length: Option<i32>,
+    #[description = "Whether podcast episodes can be queued"] allow_podcasts: Option<bool>,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let mut settings = db_get_settings(&ctx.data().pool, guild).await?;
+    let old = queue_summary(&settings);
+
+    if let Some(v) = collection_limit {
+        settings.collection_limit = v;
+    }
+    if let Some(v) = allow_explicit {
+        settings.allow_explicit = v;
+    }
+    if let Some(v) = max_length {
+        settings.max_duration = (v > 0).then_some(v * 60);
+    }
+    if let Some(v) = allow_podcasts {
+        settings.allow_podcasts = v;
+    }
+    db_save_settings(&ctx.data().pool, guild, &settings).await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Settings)
+            .target("Queue")
+            .from(old)
+            .to(queue_summary(&settings)),
+    )
+    .await?;
+
+    let max_length = match settings.max_duration {
+        Some(v) => format_delta(TimeDelta::seconds(v as i64)),
+        None => "No limit".to_string(),
+    };
+    let embed = CreateEmbed::new()
+        .colour(Colour::BLUE)
+        .title("Queue Settings")
+        .field(
+            "Album & Playlist Limit",
+            format!("{} songs", settings.collection_limit),
+            true,
+        )
+        .field(
+            "Explicit Songs",
+            if settings.allow_explicit {
+                "Allowed"
+            } else {
+                "Not allowed"
+            },
+            true,
+        )
+        .field("Max Length", max_length, true)
+        .field(
+            "Podcasts",
+            if settings.allow_podcasts {
+                "Allowed"
+            } else {
+                "Not allowed"
+            },
+            true,
+        )
+        .timestamp(Timestamp::now())
+        .footer(CreateEmbedFooter::new("Delegatify"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Choose a channel the bot posts what happens in, and what it posts
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_settings",
+    category = "Utilities"
+)]
+pub async fn log_settings(
+    ctx: Context<'_>,
+    #[description = "Channel to post in"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+    #[description = "Stop posting in the log channel"] disable: Option<bool>,
+    #[description = "Kind of event to turn on or off"] event: Option<LogEvent>,
+    #[description = "Whether that kind of event is posted"] enabled: Option<bool>,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let mut settings = db_get_log_settings(&ctx.data().pool, guild).await?;
+    let old = log_summary(&settings);
+
+    if let Some(v) = channel {
+        settings.channel_id = Some(v.id.get() as i64);
+    }
+    if disable == Some(true) {
+        settings.channel_id = None;
+    }
+    match (event, enabled) {
+        (Some(event), Some(enabled)) => event.set_enabled(&mut settings, enabled),
+        (None, None) => (),
+        _ => {
+            ctx.say("Choose both an event and whether it's posted")
+                .await?;
+            return Ok(());
+        }
+    }
+    db_save_log_settings(&ctx.data().pool, guild, &settings).await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Settings)
+            .target("Log channel")
+            .from(old)
+            .to(log_summary(&settings)),
+    )
+    .await?;
+
+    let on_off = |v: bool| if v { "On" } else { "Off" };
+    let channel = match settings.channel_id {
+        Some(v) => format!("<#{v}>"),
+        None => "None".to_string(),
+    };
+    let embed = LogEvent::ALL
+        .iter()
+        .fold(
+            CreateEmbed::new()
+                .colour(Colour::BLUE)
+                .title("Log Channel")
+                .field("Channel", channel, false),
+            |embed, v| embed.field(v.name(), on_off(v.is_enabled(&settings)), true),
+        )
+        .timestamp(Timestamp::now())
+        .footer(CreateEmbedFooter::new("Delegatify"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Stop a song, artist or album from being queued
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_blocklist",
+    category = "Utilities"
+)]
+pub async fn block(
+    ctx: Context<'_>,
+    #[description = "Link to a song, artist or album"]
+    #[max_length = 512]
+    target: String,
+) -> Result<(), Error> {
+    let (kind, id) = match blocklist::parse_target(&target) {
+        Some(v) => v,
+        None => {
+            ctx.say("Only links to songs, artists and albums can be blocked")
+                .await?;
+            return Ok(());
+        }
+    };
+    let client = match spotify::client(ctx).await {
+        Some(v) => v,
+        None => {
+            error_unauthorized(ctx).await?;
+            return Ok(());
+        }
+    };
+
+    // Names are saved so the blocklist can be shown without asking Spotify
+    let name = match kind {
+        BlockKind::Track => {
+            let track = client.track(TrackId::from_id(&id)?, None).await?;
+            StandardItem::parse(PlayableItem::Track(track)).get_title()
+        }
+        BlockKind::Artist => client.artist(ArtistId::from_id(&id)?).await?.name,
+        BlockKind::Album => client.album(AlbumId::from_id(&id)?, None).await?.name,
+    };
+
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    db_add_block(&ctx.data().pool, guild, kind.as_str(), &id, &name).await?;
+    ctx.say(format!("Blocked {name}")).await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Blocklist)
+            .target(format!("{name} ({kind})"))
+            .to("Blocked"),
+    )
+    .await?;
+
+    // Just some logging
+    info!(
+        "{} blocked the {kind} {name}",
+        user_to_id(ctx.author().id).await
+    );
+    Ok(())
+}
+
+/// Allow something on the blocklist to be queued again
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_blocklist",
+    category = "Utilities"
+)]
+pub async fn unblock(
+    ctx: Context<'_>,
+    #[description = "Link to a song, artist or album"]
+    #[max_length = 512]
+    #[autocomplete = "autocomplete_blocked"]
+    target: String,
+) -> Result<(), Error> {
+    let (kind, id) = match blocklist::parse_target(&target) {
+        Some(v) => v,
+        None => {
+            ctx.say("Only links to songs, artists and albums can be blocked")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    if db_remove_block(&ctx.data().pool, guild, kind.as_str(), &id).await? {
+        ctx.say("Removed it from the blocklist").await?;
+        audit(
+            ctx,
+            Event::new(AuditAction::Blocklist)
+                .target(format!("spotify:{kind}:{id}"))
+                .from("Blocked")
+                .to("Unblocked"),
+        )
+        .await?;
+    } else {
+        ctx.say("That isn't blocked").await?;
+    }
+
+    Ok(())
+}
+
+/// Show everything that can't be queued
+#[poise::command(slash_command, guild_only, user_cooldown = 10, category = "Utilities")]
+pub async fn blocklist(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let blocked = db_get_blocklist(&ctx.data().pool, guild).await?;
+    if blocked.is_empty() {
+        ctx.say("Nothing is blocked").await?;
+        return Ok(());
+    }
+
+    let pages = blocked
+        .chunks(BLOCKLIST_PAGE_SIZE)
+        .map(|chunk| {
+            let entries = chunk
+                .iter()
+                .map(|v| {
+                    format!(
+                        "**[{}](https://open.spotify.com/{}/{})** · {}",
+                        v.name, v.kind, v.item_id, v.kind
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            CreateEmbed::new()
+                .colour(Colour::DARK_RED)
+                .title("Blocklist")
+                .description(entries.join("\n"))
+                .timestamp(Timestamp::now())
+        })
+        .collect();
+
+    paginate(ctx, pages).await
+}
+
+/// Stop playback from being changed
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("freeze_on", "freeze_off", "freeze_until", "freeze_schedule"),
+    subcommand_required,
+    category = "Utilities"
+)]
+pub async fn freeze(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Freeze playback until turned off
+#[poise::command(slash_command, guild_only, rename = "on", check = "can_freeze")]
+pub async fn freeze_on(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let mut settings = db_get_settings(&ctx.data().pool, guild).await?;
+    let old = freeze_summary(&settings);
+
+    settings.frozen = true;
+    settings.frozen_until = None;
+    db_save_settings(&ctx.data().pool, guild, &settings).await?;
+
+    ctx.say("Enabled Freeze").await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Freeze)
+            .from(old)
+            .to(freeze_summary(&settings)),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Stop freezing playback; Scheduled freezes still apply
+#[poise::command(slash_command, guild_only, rename = "off", check = "can_freeze")]
+pub async fn freeze_off(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    let guild = guild_to_id(guild_id).await;
+    let mut settings = db_get_settings(&ctx.data().pool, guild).await?;
+    let old = freeze_summary(&settings);
+
+    settings.frozen = false;
+    settings.frozen_until = None;
+    db_save_settings(&ctx.data().pool, guild, &settings).await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Freeze)
+            .from(old)
+            .to(freeze_summary(&settings)),
+    )
+    .await?;
+
+    if ctx.data().is_frozen(guild_id).await? {
+        ctx.say("Disabled Freeze; a scheduled freeze is still active")
+            .await?;
+    } else {
+        ctx.say("Disabled Freeze").await?;
+    }
+    Ok(())
+}
+
+/// Freeze playback until a time of day
+#[poise::command(slash_command, guild_only, rename = "until", check = "can_freeze")]
+pub async fn freeze_until(
+    ctx: Context<'_>,
+    #[description = "Time the freeze ends, like 17:30"]
+    #[max_length = 5]
+    time: String,
+    #[description = "Timezone of the time, like America/New_York; Defaults to UTC"]
+    #[autocomplete = "autocomplete_timezone"]
+    timezone: Option<String>,
+) -> Result<(), Error> {
+    let timezone: Tz = match timezone.as_deref().unwrap_or("UTC").parse() {
+        Ok(v) => v,
+        Err(_) => {
+            ctx.say("Unknown timezone; use a name like America/New_York")
+                .await?;
+            return Ok(());
+        }
+    };
+    let until = match freeze::parse_time(&time)
+        .and_then(|v| freeze::next_occurrence(v, timezone, Utc::now()))
+    {
+        Some(v) => v,
+        None => {
+            ctx.say("Times look like 17:30 (hours:minutes)").await?;
+            return Ok(());
+        }
+    };
+
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let mut settings = db_get_settings(&ctx.data().pool, guild).await?;
+    let old = freeze_summary(&settings);
+    settings.frozen = true;
+    settings.frozen_until = Some(until);
+    db_save_settings(&ctx.data().pool, guild, &settings).await?;
+
+    ctx.say(format!("Enabled Freeze until <t:{}:t>", until.timestamp()))
+        .await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Freeze)
+            .from(old)
+            .to(freeze_summary(&settings)),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Freeze playback at the same times every week
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "schedule",
+    subcommands(
+        "freeze_schedule_add",
+        "freeze_schedule_list",
+        "freeze_schedule_remove"
+    ),
+    subcommand_required
+)]
+pub async fn freeze_schedule(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Add a time every week that playback is frozen
+#[poise::command(slash_command, guild_only, rename = "add", check = "can_freeze")]
+pub async fn freeze_schedule_add(
+    ctx: Context<'_>,
+    #[description = "daily, weekdays, weekends, or days like mon,wed,fri"]
+    #[max_length = 64]
+    days: String,
+    #[description = "Time the freeze starts, like 09:00"]
+    #[max_length = 5]
+    starts: String,
+    #[description = "Time the freeze ends, like 17:00"]
+    #[max_length = 5]
+    ends: String,
+    #[description = "Timezone of the times, like America/New_York; Defaults to UTC"]
+    #[autocomplete = "autocomplete_timezone"]
+    timezone: Option<String>,
+) -> Result<(), Error> {
+    let timezone = timezone.unwrap_or_else(|| "UTC".to_string());
+    if timezone.parse::<Tz>().is_err() {
+        ctx.say("Unknown timezone; use a name like America/New_York")
+            .await?;
+        return Ok(());
+    }
+    let days = match freeze::parse_days(&days) {
+        Some(v) if v != 0 => v,
+        _ => {
+            ctx.say("Days look like weekdays, or mon,wed,fri").await?;
+            return Ok(());
+        }
+    };
+    let (starts, ends) = match (freeze::parse_time(&starts), freeze::parse_time(&ends)) {
+        (Some(starts), Some(ends)) => (starts, ends),
+        _ => {
+            ctx.say("Times look like 17:30 (hours:minutes)").await?;
+            return Ok(());
+        }
+    };
+
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let id = db_add_freeze_schedule(&ctx.data().pool, guild, days, starts, ends, &timezone).await?;
+
+    let schedule = format!(
+        "{} {}–{} {timezone}",
+        freeze::format_days(days),
+        starts.format("%H:%M"),
+        ends.format("%H:%M")
+    );
+    ctx.say(format!("Added freeze #{id}: {schedule}")).await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::FreezeSchedule)
+            .target(format!("#{id}"))
+            .to(schedule),
+    )
+    .await?;
+    Ok(())
+}
+
+/// List the times playback is frozen every week
+#[poise::command(slash_command, guild_only, rename = "list", check = "can_freeze")]
+pub async fn freeze_schedule_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let schedules = db_get_freeze_schedules(&ctx.data().pool, guild).await?;
+    if schedules.is_empty() {
+        ctx.say("No freezes are scheduled").await?;
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let entries = schedules
+        .iter()
+        .map(|schedule| {
+            let active = if freeze::in_window(schedule, now) {
+                " · Active"
+            } else {
+                ""
+            };
+            format!(
+                "**#{}** {} {}–{} {}{active}",
+                schedule.id,
+                freeze::format_days(schedule.days),
+                schedule.starts.format("%H:%M"),
+                schedule.ends.format("%H:%M"),
+                schedule.timezone
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let embed = CreateEmbed::new()
+        .colour(Colour::BLUE)
+        .title("Scheduled Freezes")
+        .description(entries.join("\n"))
+        .timestamp(Timestamp::now())
+        .footer(CreateEmbedFooter::new("Delegatify"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Remove a scheduled freeze
+#[poise::command(slash_command, guild_only, rename = "remove", check = "can_freeze")]
+pub async fn freeze_schedule_remove(
+    ctx: Context<'_>,
+    #[description = "Number of the freeze from /freeze schedule list"] id: i64,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    if db_remove_freeze_schedule(&ctx.data().pool, guild, id).await? {
+        ctx.say(format!("Removed freeze #{id}")).await?;
+        audit(
+            ctx,
+            Event::new(AuditAction::FreezeSchedule)
+                .target(format!("#{id}"))
+                .to("Removed"),
+        )
+        .await?;
+    } else {
+        ctx.say(format!("No freeze #{id} is scheduled")).await?;
+    }
+
+    Ok(())
+}
+
+/// Allow a user with a role, or change the role of an added user
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_users",
+    category = "Utilities"
+)]
+pub async fn add_user(
+    ctx: Context<'_>,
+    #[description = "Person to add"] user: serenity::User,
+    #[description = "Role to give the user; default to listener"]
+    #[autocomplete = "autocomplete_role"]
+    role: Option<String>,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let id = user_to_id(user.clone().id).await;
+    let role = role.unwrap_or_else(|| "listener".to_string());
+
+    let roles = db_get_roles(&ctx.data().pool).await?;
+    let found = match roles.iter().find(|v| v.name == role) {
+        Some(v) => v,
+        None => {
+            ctx.say(format!("There is no role named '{role}'")).await?;
+            return Ok(());
+        }
+    };
+    if !can_assign(ctx, found).await? || !can_manage(ctx, &user).await? {
+        return Ok(());
+    }
+
+    let old = match db_user_exists(&ctx.data().pool, guild, id).await? {
+        true => access::user_role(ctx.data(), guild_id(ctx)?, user.id, &[]).await?,
+        false => None,
+    };
+    db_add_user(&ctx.data().pool, guild, id, &role).await?;
+    if old.is_some() {
+        ctx.say(format!("Changed user's role to {role}")).await?;
+    } else {
+        ctx.say(format!("Successfully added user as {role}"))
+            .await?;
+    }
+
+    let mut event = Event::new(AuditAction::AddUser)
+        .target(format!("<@{}>", user.id))
+        .to(role);
+    if let Some(old) = old {
+        event = event.from(old.name);
+    }
+    audit(ctx, event).await?;
+    Ok(())
+}
+
+/// Allow a user with specific permissions
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_users",
+    category = "Utilities"
+)]
+pub async fn remove_user(
+    ctx: Context<'_>,
+    #[description = "Person to remove"] user: serenity::User,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let id = user_to_id(user.clone().id).await;
+
+    if !db_user_exists(&ctx.data().pool, guild, id).await? {
+        ctx.say("User isn't in database").await?;
+        return Ok(());
+    }
+    if !can_manage(ctx, &user).await? {
+        return Ok(());
+    }
+
+    let old = access::user_role(ctx.data(), guild_id(ctx)?, user.id, &[]).await?;
+    db_remove_user(&ctx.data().pool, guild, id).await?;
+    ctx.say("Successfully removed user").await?;
+
+    let mut event = Event::new(AuditAction::RemoveUser).target(format!("<@{}>", user.id));
+    if let Some(old) = old {
+        event = event.from(old.name);
+    }
+    audit(ctx, event).await?;
+    Ok(())
+}
+
+/// Give every member of a Discord role one of the bot's roles
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_users",
+    category = "Utilities"
+)]
+pub async fn grant_role(
+    ctx: Context<'_>,
+    #[description = "Discord role to grant permissions to"] discord_role: serenity::Role,
+    #[description = "Role to give its members"]
+    #[autocomplete = "autocomplete_role"]
+    role: String,
+) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    let guild = guild_to_id(guild_id).await;
+
+    // The @everyone role shares the server's id
+    if discord_role.id.get() == guild_id.get() {
+        ctx.say("Permissions can't be granted to @everyone; use /add_user")
+            .await?;
+        return Ok(());
+    }
+
+    let roles = db_get_roles(&ctx.data().pool).await?;
+    let found = match roles.iter().find(|v| v.name == role) {
+        Some(v) => v,
+        None => {
+            ctx.say(format!("There is no role named '{role}'")).await?;
+            return Ok(());
+        }
+    };
+    if !can_assign(ctx, found).await? {
+        return Ok(());
+    }
+
+    db_add_role_grant(
+        &ctx.data().pool,
+        guild,
+        role_to_id(discord_role.id).await,
+        &role,
+    )
+    .await?;
+    ctx.say(format!("Members of {} are now {role}", discord_role.name))
+        .await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::GrantRole)
+            .target(format!("<@&{}>", discord_role.id))
+            .to(role),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Stop giving members of a Discord role permissions
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_users",
+    category = "Utilities"
+)]
+pub async fn revoke_role(
+    ctx: Context<'_>,
+    #[description = "Discord role to revoke permissions from"] discord_role: serenity::Role,
+) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let discord_role_id = role_to_id(discord_role.id).await;
+
+    // Grants of roles that aren't below the author's can only be revoked by owners
+    let grants = db_get_role_grants(&ctx.data().pool, guild).await?;
+    if let Some((_, granted)) = grants.iter().find(|(id, _)| *id == discord_role_id) {
+        let roles = db_get_roles(&ctx.data().pool).await?;
+        if let Some(role) = roles.iter().find(|v| &v.name == granted) {
+            let actor = Actor::from_context(ctx).await?;
+            if !access::can_assign(ctx.data(), &actor, role).await? {
+                ctx.say(format!(
+                    "Only owners can revoke {}, since it isn't below your role",
+                    role.name
+                ))
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    if !db_remove_role_grant(&ctx.data().pool, guild, discord_role_id).await? {
+        ctx.say("Role doesn't have any permissions").await?;
+        return Ok(());
+    }
+
+    ctx.say(format!("Revoked permissions from {}", discord_role.name))
+        .await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::RevokeRole).target(format!("<@&{}>", discord_role.id)),
+    )
+    .await?;
+    Ok(())
+}
+
+/// List which Discord roles have permissions
+#[poise::command(
+    slash_command,
+    guild_only,
+    check = "can_manage_users",
+    category = "Utilities"
+)]
+pub async fn role_grants(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let grants = db_get_role_grants(&ctx.data().pool, guild).await?;
+    let roles = db_get_roles(&ctx.data().pool).await?;
+
+    let grants = if grants.is_empty() {
+        "No Discord roles have permissions".to_string()
+    } else {
+        grants
+            .iter()
+            .map(|(discord_role, role)| format!("<@&{discord_role}> → **{role}**"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let roles = roles
+        .iter()
+        .map(|role| {
+            let mut capabilities = role
+                .capabilities
+                .iter()
+                .map(|v| v.as_str())
+                .collect::<Vec<_>>();
+            capabilities.sort();
+            format!(
+                "**{}** (level {}): {}",
+                role.name,
+                role.level,
+                capabilities.join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
 
-    if db_user_exists(&ctx.data().pool, id).await? {
-        ctx.say("User already added").await?;
-        return Ok(());
-    }
+    let embed = CreateEmbed::new()
+        .colour(Colour::BLUE)
+        .title("Role Grants")
+        .field("Discord Roles", grants, false)
+        .field("Available Roles", roles, false)
+        .timestamp(Timestamp::now())
+        .footer(CreateEmbedFooter::new("Delegatify"));
 
-    db_add_user(&ctx.data().pool, id, level).await?;
-    ctx.say("Successfully added user").await?;
+    ctx.send(CreateReply::default().embed(embed)).await?;
     Ok(())
 }
 
-/// Allow a user with specific permissions
-#[poise::command(slash_command, owners_only, category = "Utilities")]
-pub async fn remove_user(
+/// Show who changed playback, settings and permissions
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "audit",
+    check = "can_view_audit",
+    category = "Utilities"
+)]
+pub async fn audit_log(
     ctx: Context<'_>,
-    #[description = "Person to remove"] user: serenity::User,
+    #[description = "Only show what this person did"] user: Option<serenity::User>,
+    #[description = "Only show this action"] action: Option<AuditAction>,
+    #[description = "Earliest day to show, like 2025-01-31 (UTC)"]
+    #[max_length = 10]
+    since: Option<String>,
+    #[description = "Latest day to show, like 2025-01-31 (UTC)"]
+    #[max_length = 10]
+    until: Option<String>,
 ) -> Result<(), Error> {
-    let id = user_to_id(user.clone().id).await;
+    let parse_day = |v: &str| NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d").ok();
+    let since = match since.as_deref().map(parse_day) {
+        Some(None) => {
+            ctx.say("Days look like 2025-01-31 (year-month-day)")
+                .await?;
+            return Ok(());
+        }
+        v => v.flatten(),
+    };
+    let until = match until.as_deref().map(parse_day) {
+        Some(None) => {
+            ctx.say("Days look like 2025-01-31 (year-month-day)")
+                .await?;
+            return Ok(());
+        }
+        v => v.flatten(),
+    };
 
-    if !db_user_exists(&ctx.data().pool, id).await? {
-        ctx.say("User isn't in database").await?;
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let entries = db_get_audit(
+        &ctx.data().pool,
+        guild,
+        match &user {
+            Some(user) => Some(user_to_id(user.id).await),
+            None => None,
+        },
+        action.map(|v| v.as_str()),
+        since.map(|v| v.and_time(NaiveTime::MIN).and_utc()),
+        // Until includes the whole day
+        until.map(|v| (v + TimeDelta::days(1)).and_time(NaiveTime::MIN).and_utc()),
+        AUDIT_LIMIT,
+    )
+    .await?;
+    if entries.is_empty() {
+        ctx.say("Nothing matches in the audit log").await?;
         return Ok(());
     }
 
-    db_remove_user(&ctx.data().pool, id).await?;
-    ctx.say("Successfully removed user").await?;
-    Ok(())
+    let pages = entries
+        .chunks(AUDIT_PAGE_SIZE)
+        .map(|chunk| {
+            let entries = chunk
+                .iter()
+                .map(|v| {
+                    let action = match v.action.parse::<AuditAction>() {
+                        Ok(action) => action.name().to_string(),
+                        Err(_) => v.action.clone(),
+                    };
+                    let details = audit::describe(
+                        v.target.as_deref(),
+                        v.old_value.as_deref(),
+                        v.new_value.as_deref(),
+                    );
+                    format!(
+                        "<t:{}:f> <@{}> **{action}** {details}",
+                        v.created_at.timestamp(),
+                        v.actor_id
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            CreateEmbed::new()
+                .colour(Colour::BLUE)
+                .title("Audit Log")
+                .description(entries.join("\n"))
+                .timestamp(Timestamp::now())
+        })
+        .collect();
+
+    paginate(ctx, pages).await
 }
 
-/// Authenticates the application with specified token
-#[poise::command(slash_command, owners_only, category = "Utilities")]
+/// Links the server's Spotify account; The link is sent privately and finishes by itself once approved
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    category = "Utilities"
+)]
 pub async fn authenticate(ctx: Context<'_>) -> Result<(), Error> {
-    let mut spotify = spotify::init().await?;
+    let guild_id = guild_id(ctx)?;
+    let mut spotify = spotify::init(
+        &ctx.data().pool,
+        &ctx.data().cipher,
+        guild_id,
+        Some(ctx.author().id),
+    )
+    .await?;
     let url = spotify.get_authorize_url(None).unwrap();
 
+    // Spotify redirects back with the state, which ties the callback to this person
+    let state = spotify.get_oauth().state.clone();
+    callback::start(
+        &ctx.data().pending_auth,
+        state,
+        PendingAuth {
+            guild_id,
+            user_id: ctx.author().id,
+            channel_id: ctx.channel_id(),
+            client: spotify,
+            started: Instant::now(),
+        },
+    )
+    .await;
+
     let reply = {
         let components = vec![serenity::CreateActionRow::Buttons(vec![
             serenity::CreateButton::new_link(url)
                 .label("Open URL")
                 .style(poise::serenity_prelude::ButtonStyle::Primary),
-            serenity::CreateButton::new("open_modal")
-                .label("Authenticate")
-                .style(poise::serenity_prelude::ButtonStyle::Success),
         ])];
 
         poise::CreateReply::default()
@@ -321,42 +2138,12 @@ pub async fn authenticate(ctx: Context<'_>) -> Result<(), Error> {
             .description(
                     "In order for the application to work, a spotify account must be connected",
             )
-            .field("Open URL Button", "This button opens a link to recieve an authentication code. When you recieve the code, click on the Authenticate button.", false)
-            .field("Authenticate Button", "This is the button you click when you have the code. It will ask you to input the code, and then you are good to go.", false)
+            .field("Open URL Button", format!("This button opens Spotify to allow access. Once you do, Delegatify confirms here by itself. The link works for {} minutes.", callback::AUTH_TIMEOUT.as_secs() / 60), false)
             .footer(CreateEmbedFooter::new(format!("Version: {}", env!("CARGO_PKG_VERSION")))))
             .components(components)
     };
 
     ctx.send(reply).await?;
-
-    while let Some(mci) = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
-        .timeout(std::time::Duration::from_secs(120))
-        .author_id(ctx.author().id)
-        .channel_id(ctx.channel_id())
-        .filter(move |mci| mci.data.custom_id == "open_modal")
-        .await
-    {
-        let data = poise::execute_modal_on_component_interaction::<SpotifyAuthenticationModal>(
-            ctx, mci, None, None,
-        )
-        .await?;
-
-        if let Some(v) = data {
-            info!("Recieved Code");
-            let borrow = &spotify;
-            borrow
-                .request_token(&v.code)
-                .await
-                .map_err(|err| format!("Failed to Authenticate:\n{err}"))?;
-            debug!("Requested Token");
-
-            ctx.reply(format!("Successfully Authenticated!")).await?;
-
-            *ctx.data().spotify.write().await = Some(spotify.clone());
-        } else {
-            ctx.reply("No Input provided").await?;
-        }
-    }
     Ok(())
 }
 
@@ -366,9 +2153,7 @@ pub async fn authenticate(ctx: Context<'_>) -> Result<(), Error> {
 
 /// Inner command of current
 async fn run_current(ctx: Context<'_>) -> Result<(), Error> {
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
+    let client = match spotify::client(ctx).await {
         Some(v) => v,
         None => {
             error_unauthorized(ctx).await?;
@@ -384,8 +2169,6 @@ async fn run_current(ctx: Context<'_>) -> Result<(), Error> {
             return Ok(());
         }
     };
-    // Force drop to allow for other requests
-    drop(lock);
 
     let embed = CreateEmbed::new();
 
@@ -399,8 +2182,27 @@ async fn run_current(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Formats an item of the queue on one line, with who requested it if it was through the bot
+fn format_queue_entry(
+    position: usize,
+    item: &StandardItem<'_>,
+    requester: Option<UserId>,
+) -> String {
+    let mut entry = format!(
+        "`{position}.` [{}]({}) · {}",
+        item.get_title(),
+        item.url,
+        format_delta(item.duration)
+    );
+    if let Some(requester) = requester {
+        entry.push_str(&format!(" · <@{requester}>"));
+    }
+
+    entry
+}
+
 /// If there is a currently playing song
-async fn current_playback(
+pub async fn current_playback(
     playback: &CurrentPlaybackContext,
     item: PlayableItem,
     embed: CreateEmbed,
@@ -409,7 +2211,8 @@ async fn current_playback(
 
     let progress = playback.progress.unwrap();
     let duration = format!(
-        "{} / {}",
+        "{}\n{} / {}",
+        progress_bar(progress, item.duration),
         format_delta(progress),
         format_delta(item.duration)
     );
@@ -435,8 +2238,23 @@ async fn current_playback(
         .field("Repeat", repeat, true)
 }
 
+/// Draws how far playback is through the item
+fn progress_bar(progress: TimeDelta, duration: TimeDelta) -> String {
+    let filled = match duration.num_milliseconds() {
+        0 => 0,
+        total => (progress.num_milliseconds() * PROGRESS_BAR_LENGTH / total)
+            .clamp(0, PROGRESS_BAR_LENGTH - 1),
+    };
+
+    format!(
+        "{}🔘{}",
+        "▬".repeat(filled as usize),
+        "▬".repeat((PROGRESS_BAR_LENGTH - filled - 1) as usize)
+    )
+}
+
 /// If there is no song playing
-async fn current_no_playback(embed: CreateEmbed) -> CreateEmbed {
+pub async fn current_no_playback(embed: CreateEmbed) -> CreateEmbed {
     // Create Embed
     embed
         .color(Colour::DARK_RED)
@@ -446,17 +2264,307 @@ async fn current_no_playback(embed: CreateEmbed) -> CreateEmbed {
         .description("Nothing is currently being played ")
 }
 
-/// Parse a URL for TrackId
-async fn play_url<'a>(url: &'a str) -> Result<TrackId<'a>, IdError> {
-    let id = url.split('/').last().unwrap().split('?').next().unwrap();
-    TrackId::from_id(id)
+/// Opens a vote to skip the current song, skipping once enough people voted
+async fn run_vote_skip(
+    ctx: Context<'_>,
+    client: &AuthCodePkceSpotify,
+    settings: &GuildSettings,
+) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+
+    // Only one vote can run at a time
+    if !ctx.data().skip_votes.write().await.insert(guild_id) {
+        ctx.say("A vote to skip is already running").await?;
+        return Ok(());
+    }
+    let result = run_vote_skip_inner(ctx, client, settings).await;
+    ctx.data().skip_votes.write().await.remove(&guild_id);
+
+    result
+}
+
+/// Inner function of run_vote_skip
+async fn run_vote_skip_inner(
+    ctx: Context<'_>,
+    client: &AuthCodePkceSpotify,
+    settings: &GuildSettings,
+) -> Result<(), Error> {
+    let item = match client.current_playing(None, None::<Vec<_>>).await? {
+        Some(v) if v.item.is_some() => StandardItem::parse(v.item.unwrap()),
+        _ => {
+            ctx.say("Nothing Playing; can't modify playback.").await?;
+            return Ok(());
+        }
+    };
+
+    let voice = owner_voice_channel(ctx).await?;
+    if settings.vote_voice_only {
+        let channel = match voice {
+            Some((channel, _)) => channel,
+            None => {
+                ctx.say("The owner isn't in a voice channel, so nobody can vote")
+                    .await?;
+                return Ok(());
+            }
+        };
+        if !in_voice_channel(ctx, ctx.author().id, channel) {
+            ctx.say(format!("Join <#{channel}> to vote")).await?;
+            return Ok(());
+        }
+    }
+
+    let required = match (settings.vote_percentage, voice) {
+        (Some(percentage), Some((_, listeners))) => {
+            (listeners * percentage as usize).div_ceil(100).max(1)
+        }
+        _ => settings.vote_threshold.max(1) as usize,
+    };
+    let mut votes = HashSet::from([ctx.author().id]);
+
+    // Wait for enough votes unless the person starting the vote is enough
+    let passed = if votes.len() >= required {
+        true
+    } else {
+        let reply = ctx
+            .send(
+                CreateReply::default()
+                    .embed(vote_embed(&item, votes.len(), required))
+                    .components(vote_components(votes.len(), required)),
+            )
+            .await?;
+        let message = reply.message().await?.id;
+
+        let deadline = Instant::now() + Duration::from_secs(settings.vote_timeout as u64);
+        let mut passed = false;
+        while let Some(mci) = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
+            .timeout(deadline.saturating_duration_since(Instant::now()))
+            .message_id(message)
+            .custom_ids(vec!["vote_skip".to_string()])
+            .await
+        {
+            let voter = mci.user.id;
+            let eligible = match voice {
+                Some((channel, _)) if settings.vote_voice_only => {
+                    in_voice_channel(ctx, voter, channel)
+                }
+                _ => true,
+            };
+
+            let response = if !eligible {
+                ephemeral_response("Join the owner's voice channel to vote")
+            } else if !votes.insert(voter) {
+                ephemeral_response("You already voted")
+            } else if votes.len() >= required {
+                passed = true;
+                CreateInteractionResponse::Acknowledge
+            } else {
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(vote_embed(&item, votes.len(), required))
+                        .components(vote_components(votes.len(), required)),
+                )
+            };
+            mci.create_response(ctx.http(), response).await?;
+
+            if passed {
+                break;
+            }
+        }
+
+        let result = if passed { "Vote passed" } else { "Vote failed" };
+        reply
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .embed(
+                        vote_embed(&item, votes.len(), required)
+                            .author(CreateEmbedAuthor::new(result)),
+                    )
+                    .components(vec![]),
+            )
+            .await?;
+        passed
+    };
+
+    if !passed {
+        return Ok(());
+    }
+
+    // Don't skip a different song if the voted one already ended
+    let still_playing = match client.current_playing(None, None::<Vec<_>>).await? {
+        Some(v) => v
+            .item
+            .is_some_and(|v| StandardItem::parse(v).url == item.url),
+        None => false,
+    };
+    if !still_playing {
+        ctx.say("The song already changed; nothing was skipped")
+            .await?;
+        return Ok(());
+    }
+
+    service::next_track(ctx.data(), guild_id(ctx)?, client).await?;
+    run_current(ctx).await?;
+    audit(
+        ctx,
+        Event::new(AuditAction::Skip)
+            .target(item.get_title())
+            .to(format!("Vote passed with {}", votes.len())),
+    )
+    .await?;
+
+    // Just some logging
+    info!("{} votes skipped {}", votes.len(), item.get_title());
+    Ok(())
+}
+
+/// Embed showing the progress of a vote to skip
+fn vote_embed(item: &StandardItem<'_>, votes: usize, required: usize) -> CreateEmbed {
+    CreateEmbed::new()
+        .colour(Colour::GOLD)
+        .author(CreateEmbedAuthor::new("Vote To Skip"))
+        .title(item.get_title())
+        .thumbnail(item.image.clone())
+        .field("Votes", format!("{votes} / {required}"), true)
+        .timestamp(Timestamp::now())
+        .footer(CreateEmbedFooter::new("Delegatify"))
+}
+
+/// Button to vote with
+fn vote_components(votes: usize, required: usize) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![CreateButton::new(
+        "vote_skip",
+    )
+    .label(format!("Skip ({votes}/{required})"))
+    .style(ButtonStyle::Primary)])]
+}
+
+/// Response only the person who clicked can see
+fn ephemeral_response(content: &str) -> CreateInteractionResponse {
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true),
+    )
+}
+
+/// Returns the voice channel of who connected the server's Spotify, and how many people are in it
+async fn owner_voice_channel(ctx: Context<'_>) -> Result<Option<(ChannelId, usize)>, Error> {
+    let guild = guild_to_id(guild_id(ctx)?).await;
+    let owner = match db_get_token_owner(&ctx.data().pool, guild).await? {
+        Some(v) => UserId::new(v as u64),
+        None => return Ok(None),
+    };
+
+    let guild = match ctx.guild() {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let channel = match guild.voice_states.get(&owner).and_then(|v| v.channel_id) {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    // Bots don't get a vote
+    let listeners = guild
+        .voice_states
+        .values()
+        .filter(|v| v.channel_id == Some(channel))
+        .filter(|v| !v.member.as_ref().is_some_and(|member| member.user.bot))
+        .count();
+
+    Ok(Some((channel, listeners)))
+}
+
+/// Checks whether the user is in the voice channel
+fn in_voice_channel(ctx: Context<'_>, user: UserId, channel: ChannelId) -> bool {
+    ctx.guild().is_some_and(|guild| {
+        guild
+            .voice_states
+            .get(&user)
+            .is_some_and(|v| v.channel_id == Some(channel))
+    })
+}
+
+/// Sends the embeds as pages with buttons to switch between them
+async fn paginate(ctx: Context<'_>, pages: Vec<CreateEmbed>) -> Result<(), Error> {
+    // Unique ids so other paginations aren't affected
+    let ctx_id = ctx.id();
+    let prev_id = format!("{ctx_id}prev");
+    let next_id = format!("{ctx_id}next");
+
+    let page_embed = |index: usize| {
+        pages[index].clone().footer(CreateEmbedFooter::new(format!(
+            "Page {} / {}",
+            index + 1,
+            pages.len()
+        )))
+    };
+    let components = |index: usize| {
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(&prev_id)
+                .label("Previous")
+                .style(ButtonStyle::Secondary)
+                .disabled(index == 0),
+            CreateButton::new(&next_id)
+                .label("Next")
+                .style(ButtonStyle::Secondary)
+                .disabled(index + 1 >= pages.len()),
+        ])]
+    };
+
+    let mut current = 0;
+    let mut reply = CreateReply::default().embed(page_embed(current));
+    // No buttons needed for a single page
+    if pages.len() > 1 {
+        reply = reply.components(components(current));
+    }
+    let handle = ctx.send(reply).await?;
+    if pages.len() <= 1 {
+        return Ok(());
+    }
+
+    while let Some(press) = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
+        .custom_ids(vec![prev_id.clone(), next_id.clone()])
+        .timeout(Duration::from_secs(120))
+        .await
+    {
+        if press.data.custom_id == next_id {
+            current = (current + 1).min(pages.len() - 1);
+        } else {
+            current = current.saturating_sub(1);
+        }
+
+        press
+            .create_response(
+                ctx.http(),
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(page_embed(current))
+                        .components(components(current)),
+                ),
+            )
+            .await?;
+    }
+
+    // Remove the buttons once they stop working
+    handle
+        .edit(
+            ctx,
+            CreateReply::default()
+                .embed(page_embed(current))
+                .components(vec![]),
+        )
+        .await?;
+    Ok(())
 }
 
-/// Use search to confirm song, return TrackId
-async fn play_search(ctx: Context<'_>, input: String) -> Result<TrackId<'_>, Error> {
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
+/// Use search to confirm the choice, return what to queue
+async fn play_search(
+    ctx: Context<'_>,
+    input: String,
+    kind: SearchKind,
+) -> Result<SpotifyLink, Error> {
+    let client = match spotify::client(ctx).await {
         Some(v) => v,
         None => {
             error_unauthorized(ctx).await?;
@@ -464,30 +2572,90 @@ async fn play_search(ctx: Context<'_>, input: String) -> Result<TrackId<'_>, Err
         }
     };
 
+    let search_type = match kind {
+        SearchKind::Track => SearchType::Track,
+        SearchKind::Episode => SearchType::Episode,
+        SearchKind::Show => SearchType::Show,
+        SearchKind::Album => SearchType::Album,
+    };
     let search_result = client
-        .search(&input, SearchType::Track, None, None, Some(5), None)
+        .search(&input, search_type, None, None, Some(5), None)
         .await?;
-    // Free client
-    drop(lock);
-
-    // Make the data into standard items
-    let mut data = Vec::new();
-    if let SearchResult::Tracks(page) = search_result {
-        for item in page.items {
-            let item = StandardItem::parse(PlayableItem::Track(item));
-            // Ignore already known tracks with the same exact title
-            if !data
-                .iter()
-                .any(|existing: &StandardItem<'_>| existing.get_title() == item.get_title())
-            {
-                data.push(item)
+
+    // Make the data into labels for the buttons and what each one queues;
+    // Blocked results and those against the server's policy are hidden
+    let policy = QueuePolicy::load(&ctx.data().pool, guild_id(ctx)?).await?;
+    let blocklist = Blocklist::load(&ctx.data().pool, guild_id(ctx)?).await?;
+    let hidden =
+        |item: &StandardItem<'_>| policy.reason(item).is_some() || blocklist.reason(item).is_some();
+    let mut results: Vec<(String, SpotifyLink)> = Vec::new();
+    match search_result {
+        SearchResult::Tracks(page) => {
+            for item in page.items {
+                let item = StandardItem::parse(PlayableItem::Track(item));
+                if hidden(&item) {
+                    continue;
+                }
+                if let Some(id) = item.get_track_id() {
+                    results.push((item.get_title(), SpotifyLink::Track(id.clone_static())));
+                }
             }
         }
-    } else {
-        panic!("Not Possible");
+        SearchResult::Episodes(page) => {
+            // Spotify rejects fetching no episodes at all
+            if page.items.is_empty() {
+                return Err("No results were found".into());
+            }
+
+            // Search leaves out the show, which is needed for the title
+            let ids = page.items.into_iter().map(|v| v.id);
+            for item in client.get_several_episodes(ids, None).await? {
+                let item = StandardItem::parse(PlayableItem::Episode(item));
+                if hidden(&item) {
+                    continue;
+                }
+                if let ItemId::Episode(id) = &item.id {
+                    results.push((item.get_title(), SpotifyLink::Episode(id.clone_static())));
+                }
+            }
+        }
+        SearchResult::Shows(page) if policy.allow_podcasts => {
+            for show in page.items {
+                let title = search_title(&show.name, &show.publisher);
+                results.push((title, SpotifyLink::Show(show.id)));
+            }
+        }
+        // Every episode of a show would be refused
+        SearchResult::Shows(_) => (),
+        SearchResult::Albums(page) => {
+            for album in page.items {
+                let artist = album.artists.first().map(|v| v.name.as_str());
+                let artist_ids = album
+                    .artists
+                    .iter()
+                    .filter_map(|v| v.id.as_ref().map(|v| v.id().to_string()))
+                    .collect::<Vec<_>>();
+                if let Some(id) = album
+                    .id
+                    .filter(|v| !blocklist.blocks_album(v.id(), &artist_ids))
+                {
+                    let title = search_title(&album.name, artist.unwrap_or_default());
+                    results.push((title, SpotifyLink::Album(id)));
+                }
+            }
+        }
+        _ => return Err("Unexpected search result".into()),
     }
 
-    if data.len() == 0 {
+    // Ignore results with the same exact title
+    let mut data: Vec<(String, SpotifyLink)> = Vec::new();
+    for result in results {
+        if !data.iter().any(|existing| existing.0 == result.0) {
+            data.push(result);
+        }
+    }
+
+    if data.is_empty() {
         return Err("No results were found".into());
     }
 
@@ -497,7 +2665,7 @@ async fn play_search(ctx: Context<'_>, input: String) -> Result<TrackId<'_>, Err
 
         // Add buttons so custom id is equal to index; allows accsesing data via index
         // Take only 3 songs at most; there's guaranteed to be at least 1
-        for (index, song) in data.iter().enumerate().take(3) {
+        for (index, (title, _)) in data.iter().enumerate().take(3) {
             let style = if index == 0 {
                 ButtonStyle::Primary
             } else {
@@ -506,7 +2674,7 @@ async fn play_search(ctx: Context<'_>, input: String) -> Result<TrackId<'_>, Err
             components.push(CreateActionRow::Buttons(vec![CreateButton::new(
                 index.to_string(),
             )
-            .label(song.get_title())
+            .label(title)
             .style(style)]));
         }
 
@@ -517,12 +2685,13 @@ async fn play_search(ctx: Context<'_>, input: String) -> Result<TrackId<'_>, Err
 
         // Create the reply
         poise::CreateReply::default()
-            .content("Choose A Song To Play")
+            .content(format!("Choose {} To Play", kind.prompt()))
             .components(components)
     };
     ctx.send(reply).await.context("Failed to send message")?;
 
     // Sort component interactions; Trys to convert id to int to classify it as s button
+    #[allow(clippy::never_loop)]
     while let Some(mci) = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
         .timeout(std::time::Duration::from_secs(120))
         .author_id(ctx.author().id)
@@ -540,7 +2709,7 @@ async fn play_search(ctx: Context<'_>, input: String) -> Result<TrackId<'_>, Err
             // If it is another item
             id => {
                 let parsed = id.parse::<usize>()?;
-                return Ok(data[parsed].get_track_id().unwrap().clone_static());
+                return Ok(data.swap_remove(parsed).1);
             }
         }
     }
@@ -549,65 +2718,307 @@ async fn play_search(ctx: Context<'_>, input: String) -> Result<TrackId<'_>, Err
     Err("No interaction".into())
 }
 
+/// Shortens a search result's title to fit in a button, like `StandardItem::get_title`
+fn search_title(name: &str, by: &str) -> String {
+    let title = format!("{name} - {by}");
+    if by.is_empty() || title.len() >= 80 {
+        return name.to_string();
+    }
+
+    title
+}
+
 /// Checks for whether a playback command should run
-async fn allow_playback(ctx: Context<'_>, min_level: i16) -> Result<bool, Error> {
-    if is_frozen(ctx).await {
-        ctx.say("Playback changes are frozen").await?;
-        return Ok(false);
+async fn allow_playback(ctx: Context<'_>, capability: Capability) -> Result<bool, Error> {
+    let actor = Actor::from_context(ctx).await?;
+    let denial = playback_denial(ctx.data(), &actor, capability).await?;
+    deny(ctx, denial).await
+}
+
+/// Like `allow_playback`, but for commands that work when nothing is playing
+async fn allow_change(ctx: Context<'_>, capability: Capability) -> Result<bool, Error> {
+    let actor = Actor::from_context(ctx).await?;
+    let denial = change_denial(ctx.data(), &actor, capability).await?;
+    deny(ctx, denial).await
+}
+
+/// Writes what the author did to the audit log, and posts it to the log channel
+async fn audit(ctx: Context<'_>, event: Event) -> Result<(), Error> {
+    let guild_id = guild_id(ctx)?;
+    audit::record(&ctx.data().pool, guild_id, ctx.author().id, &event).await?;
+    log_channel::post(
+        ctx.http(),
+        &ctx.data().pool,
+        guild_id,
+        ctx.author().id,
+        &event,
+    )
+    .await
+}
+
+/// Audit event for skipping past a song, if one was playing
+fn skip_event(action: AuditAction, skipped: Option<StandardItem<'_>>) -> Event {
+    match skipped {
+        Some(item) => Event::new(action).target(item.get_title()),
+        None => Event::new(action),
     }
-    if !is_active(ctx).await? {
-        ctx.say("Nothing Playing; can't modify playback.").await?;
-        return Ok(false);
+}
+
+/// Vote settings as shown in the audit log
+fn vote_summary(settings: &GuildSettings) -> String {
+    if !settings.vote_skip {
+        return "Off".to_string();
     }
-    if is_owner(ctx).await {
-        return Ok(true);
+
+    let required = match settings.vote_percentage {
+        Some(v) => format!("{v}%"),
+        None => format!("{} votes", settings.vote_threshold),
+    };
+    let voice = if settings.vote_voice_only {
+        ", voice only"
+    } else {
+        ""
+    };
+    format!("On, {required}, {}s{voice}", settings.vote_timeout)
+}
+
+/// Queue settings as shown in the audit log
+fn queue_summary(settings: &GuildSettings) -> String {
+    let max_length = match settings.max_duration {
+        Some(v) => format_delta(TimeDelta::seconds(v as i64)),
+        None => "no limit".to_string(),
+    };
+    format!(
+        "{} per collection, explicit {}, max length {max_length}, podcasts {}",
+        settings.collection_limit,
+        if settings.allow_explicit { "on" } else { "off" },
+        if settings.allow_podcasts { "on" } else { "off" }
+    )
+}
+
+/// Log channel settings as shown in the audit log
+fn log_summary(settings: &LogSettings) -> String {
+    let channel = match settings.channel_id {
+        Some(v) => format!("<#{v}>"),
+        None => return "Off".to_string(),
+    };
+    let events = LogEvent::ALL
+        .iter()
+        .filter(|v| v.is_enabled(settings))
+        .map(|v| v.name())
+        .collect::<Vec<_>>();
+
+    format!("{channel}: {}", events.join(", "))
+}
+
+/// Freeze state as shown in the audit log
+fn freeze_summary(settings: &GuildSettings) -> String {
+    match (settings.frozen, settings.frozen_until) {
+        (false, _) => "Off".to_string(),
+        (true, None) => "On".to_string(),
+        (true, Some(until)) => format!("On until <t:{}:t>", until.timestamp()),
     }
+}
 
-    is_allowed(ctx, min_level).await
+/// Tells the user why they were denied; Returns whether they were allowed
+async fn deny(ctx: Context<'_>, denial: Option<Reason>) -> Result<bool, Error> {
+    match denial {
+        Some(reason) => {
+            ctx.say(reason.to_string()).await?;
+            Ok(false)
+        }
+        None => Ok(true),
+    }
 }
 
-/// Returns whether playback is running or not
-async fn is_active(ctx: Context<'_>) -> Result<bool, Error> {
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
+/// Suggests the names of available Spotify devices
+async fn autocomplete_device(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let client = match spotify::client(ctx).await {
         Some(v) => v,
-        None => {
-            error_unauthorized(ctx).await?;
-            return Ok(false);
-        }
+        None => return Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    client
+        .device()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|device| device.name)
+        .filter(|name| name.to_lowercase().contains(&partial))
+        .collect()
+}
+
+/// Returns whether the user is authorised or not, telling them if they aren't
+async fn is_allowed(ctx: Context<'_>, capability: Capability) -> Result<bool, Error> {
+    let denial = match has_capability(ctx, capability).await? {
+        true => None,
+        false => Some(Reason::NoPermission),
     };
 
-    match client.current_playing(None, None::<Vec<_>>).await? {
-        Some(_) => Ok(true),
-        None => Ok(false),
+    deny(ctx, denial).await
+}
+
+/// Returns whether the author can hand out the role, telling them if they can't
+async fn can_assign(ctx: Context<'_>, role: &Role) -> Result<bool, Error> {
+    let actor = Actor::from_context(ctx).await?;
+    if access::can_assign(ctx.data(), &actor, role).await? {
+        return Ok(true);
     }
+
+    ctx.say(format!(
+        "Only owners can give out {}, since it isn't below your role",
+        role.name
+    ))
+    .await?;
+    Ok(false)
 }
 
-/// Returns whether the user is authorised or not
-async fn is_allowed(ctx: Context<'_>, min_level: i16) -> Result<bool, Error> {
-    let id = user_to_id(ctx.author().id).await;
-    match db_get_user_permission(&ctx.data().pool, id).await? {
-        Some(level) => {
-            if level < min_level {
-                ctx.say("You don't have permission to run this command")
-                    .await?;
-                return Ok(false);
-            }
-        }
-        None => {
-            ctx.say("You don't have permission to run this command")
-                .await?;
-            return Ok(false);
-        }
+/// Returns whether the author can change or remove the user, telling them if they can't
+async fn can_manage(ctx: Context<'_>, user: &serenity::User) -> Result<bool, Error> {
+    let actor = Actor::from_context(ctx).await?;
+    let roles = member_roles(ctx, user.id).await?;
+    if access::can_manage(ctx.data(), &actor, user.id, &roles).await? {
+        return Ok(true);
+    }
+
+    ctx.say(format!(
+        "Only owners can change {}, since their role isn't below yours",
+        user.name
+    ))
+    .await?;
+    Ok(false)
+}
+
+/// Returns whether the user's role grants the capability; Owners are always allowed
+async fn has_capability(ctx: Context<'_>, capability: Capability) -> Result<bool, Error> {
+    let actor = Actor::from_context(ctx).await?;
+    access::has_capability(ctx.data(), &actor, capability).await
+}
+
+/// Returns the Discord roles of someone in the server
+async fn member_roles(ctx: Context<'_>, user: UserId) -> Result<Vec<RoleId>, Error> {
+    if user == ctx.author().id {
+        return Ok(match ctx.author_member().await {
+            Some(member) => member.roles.clone(),
+            None => Vec::new(),
+        });
+    }
+
+    match guild_id(ctx)?.member(ctx, user).await {
+        Ok(member) => Ok(member.roles),
+        Err(_) => Ok(Vec::new()),
     }
+}
+
+/// Command check for freeze
+async fn can_freeze(ctx: Context<'_>) -> Result<bool, Error> {
+    is_allowed(ctx, Capability::Freeze).await
+}
+
+/// Command check for changing settings
+async fn can_manage_settings(ctx: Context<'_>) -> Result<bool, Error> {
+    is_allowed(ctx, Capability::ManageSettings).await
+}
+
+/// Command check for moving requests
+async fn can_manage_queue(ctx: Context<'_>) -> Result<bool, Error> {
+    is_allowed(ctx, Capability::ManageQueue).await
+}
+
+/// Command check for changing the blocklist
+async fn can_manage_blocklist(ctx: Context<'_>) -> Result<bool, Error> {
+    is_allowed(ctx, Capability::ManageBlocklist).await
+}
+
+/// Command check for reading the audit log
+async fn can_view_audit(ctx: Context<'_>) -> Result<bool, Error> {
+    is_allowed(ctx, Capability::ViewAudit).await
+}
+
+/// Command check for adding and removing users
+async fn can_manage_users(ctx: Context<'_>) -> Result<bool, Error> {
+    is_allowed(ctx, Capability::ManageUsers).await
+}
+
+/// Suggests blocked items; Choosing one fills in its URI
+async fn autocomplete_blocked(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<serenity::AutocompleteChoice> {
+    let guild = match ctx.guild_id() {
+        Some(v) => guild_to_id(v).await,
+        None => return Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    db_get_blocklist(&ctx.data().pool, guild)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|v| v.name.to_lowercase().contains(&partial))
+        .map(|v| {
+            let value = format!("spotify:{}:{}", v.kind, v.item_id);
+            serenity::AutocompleteChoice::new(v.name, value)
+        })
+        .collect()
+}
+
+/// Suggests timezone names
+async fn autocomplete_timezone(_ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let partial = partial.to_lowercase();
+    chrono_tz::TZ_VARIANTS
+        .iter()
+        .map(|v| v.name())
+        .filter(|name| name.to_lowercase().contains(&partial))
+        .map(String::from)
+        .collect()
+}
 
-    Ok(true)
+/// Suggests role names for the role parameter
+async fn autocomplete_role(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let roles = db_get_roles(&ctx.data().pool).await.unwrap_or_default();
+    roles
+        .into_iter()
+        .map(|role| role.name)
+        .filter(|name| name.starts_with(partial))
+        .collect()
 }
 
-/// Checks if user is an owner
-async fn is_owner(ctx: Context<'_>) -> bool {
-    ctx.framework().options.owners.contains(&ctx.author().id)
+/// Suggests songs while typing in /play; Choosing one fills in its URI
+async fn autocomplete_play(ctx: Context<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    let query = partial.trim();
+    if query.len() < 2 || SpotifyLink::parse(query).is_some() {
+        return Vec::new();
+    }
+
+    // Only search once the user stops typing; Newer keystrokes replace this one
+    let author = ctx.author().id;
+    let started = Instant::now();
+    ctx.data().typing.write().await.insert(author, started);
+    tokio::time::sleep(TYPING_DEBOUNCE).await;
+    {
+        let mut typing = ctx.data().typing.write().await;
+        if typing.get(&author) != Some(&started) {
+            return Vec::new();
+        }
+        typing.remove(&author);
+    }
+
+    let guild_id = match ctx.guild_id() {
+        Some(v) => v,
+        None => return Vec::new(),
+    };
+    match spotify::search_tracks(ctx.data(), guild_id, query).await {
+        Ok(results) => results
+            .into_iter()
+            .map(|(title, uri)| serenity::AutocompleteChoice::new(title, uri))
+            .collect(),
+        Err(err) => {
+            debug!("Failed to suggest songs for '{query}': {err}");
+            Vec::new()
+        }
+    }
 }
 
 /// Converts a UserId to i64
@@ -615,9 +3026,18 @@ async fn user_to_id(user: UserId) -> i64 {
     user.to_string().parse::<i64>().unwrap()
 }
 
+/// Converts a GuildId to i64
+async fn guild_to_id(guild: GuildId) -> i64 {
+    guild.to_string().parse::<i64>().unwrap()
+}
+
+/// Converts a RoleId to i64
+async fn role_to_id(role: RoleId) -> i64 {
+    role.to_string().parse::<i64>().unwrap()
+}
+
 /// Error 401 response for discord
 pub async fn error_unauthorized(ctx: Context<'_>) -> Result<(), Error> {
-    ctx.reply("The application isn't authenticated.\nrun '/authenticate' to connect.")
-        .await?;
+    ctx.reply(access::UNAUTHORIZED).await?;
     Ok(())
 }
@@ -0,0 +1,56 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+
+/// Length of the nonce prepended to every ciphertext
+const NONCE_LEN: usize = 12;
+
+/// Encrypts secrets (the Spotify token) before they are written to the database
+#[derive(Clone)]
+pub struct TokenCipher {
+    cipher: Aes256Gcm,
+}
+
+impl TokenCipher {
+    /// Derives a 256 bit key from the secret; any length of secret is accepted
+    pub fn new(secret: &str) -> Self {
+        let digest = Sha256::digest(secret.as_bytes());
+        let key = Key::<Aes256Gcm>::from_slice(&digest);
+
+        Self {
+            cipher: Aes256Gcm::new(key),
+        }
+    }
+
+    /// Returns the nonce followed by the ciphertext
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Failed to encrypt data")?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        Ok(data)
+    }
+
+    /// Reverses `encrypt`; fails if the key changed or the data was tampered with
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        if data.len() < NONCE_LEN {
+            return Err("Encrypted data is too short".into());
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt data")?;
+
+        Ok(plaintext)
+    }
+}
@@ -1,47 +1,163 @@
-#[allow(dead_code)]
 use sqlx::migrate::MigrateError;
+use std::collections::HashMap;
 
+use chrono::{DateTime, NaiveTime, Utc};
+
+use crate::permissions::Role;
+use crate::spotify::StandardItem;
 use crate::Error;
 
-pub enum Permissions {
-    Default,
-    Basic,
+// Row in table
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct GuildSettings {
+    pub vote_skip: bool,
+    pub vote_threshold: i16,
+    pub vote_percentage: Option<i16>,
+    /// Seconds
+    pub vote_timeout: i32,
+    pub vote_voice_only: bool,
+    /// Most songs queued from one album or playlist
+    pub collection_limit: i16,
+    /// Name of the Spotify device /play starts on when nothing is playing
+    pub preferred_device: Option<String>,
+    pub frozen: bool,
+    /// When freezing ends by itself; None if it lasts until turned off
+    pub frozen_until: Option<DateTime<Utc>>,
+    pub allow_explicit: bool,
+    /// Seconds; Longer songs can't be queued
+    pub max_duration: Option<i32>,
+    pub allow_podcasts: bool,
+}
+
+/// Matches the column defaults in the database
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            vote_skip: false,
+            vote_threshold: 3,
+            vote_percentage: None,
+            vote_timeout: 60,
+            vote_voice_only: false,
+            collection_limit: 25,
+            preferred_device: None,
+            frozen: false,
+            frozen_until: None,
+            allow_explicit: true,
+            max_duration: None,
+            allow_podcasts: true,
+        }
+    }
+}
+
+// Row in table
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LogSettings {
+    /// Where events are posted; None if they aren't
+    pub channel_id: Option<i64>,
+    pub log_queue: bool,
+    pub log_skips: bool,
+    pub log_previous: bool,
+    pub log_freeze: bool,
+    pub log_permissions: bool,
+    pub log_authentication: bool,
+}
+
+/// Matches the column defaults in the database
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            channel_id: None,
+            log_queue: true,
+            log_skips: true,
+            log_previous: true,
+            log_freeze: true,
+            log_permissions: true,
+            log_authentication: true,
+        }
+    }
+}
+
+// Row in table
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Play {
+    pub name: String,
+    pub artists: Vec<String>,
+    pub url: String,
+    pub requester: Option<i64>,
+    pub started_at: DateTime<Utc>,
+    pub ended: Option<String>,
+}
+
+// Row in table
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FreezeSchedule {
+    pub id: i64,
+    /// Bit for each day, Monday being the lowest
+    pub days: i16,
+    pub starts: NaiveTime,
+    pub ends: NaiveTime,
+    pub timezone: String,
+}
+
+// Row in table
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub actor_id: i64,
+    pub action: String,
+    pub target: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Row in table
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BlockedItem {
+    pub kind: String,
+    pub item_id: String,
+    pub name: String,
 }
+
 // Row in table
-#[derive(Debug, sqlx::FromRow)]
-struct User {
-    // #[sqlx(default)]
-    // id: Option<i64>,
-    #[sqlx(default)]
-    permission: Option<i16>,
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Quota {
+    pub max_requests: i32,
+    /// Seconds
+    pub period: i32,
 }
 
 pub async fn migrate(pool: &sqlx::PgPool) -> Result<(), MigrateError> {
     sqlx::migrate!("./migrations").run(pool).await
 }
 
+// Adds the user with a role; Replaces the role if the user was already added
 pub async fn db_add_user(
     pool: &sqlx::PgPool,
+    guild_id: i64,
     user_id: i64,
-    level: Option<i16>,
+    role: &str,
 ) -> Result<(), Error> {
-    let level = level.unwrap_or(1);
     let mut tx = pool.begin().await?;
 
-    sqlx::query("INSERT INTO users (id, permission) VALUES ($1, $2)")
-        .bind(user_id)
-        .bind(level)
-        .execute(&mut *tx)
-        .await?;
+    sqlx::query(
+        "INSERT INTO users (guild_id, id, role) VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id, id) DO UPDATE SET role = EXCLUDED.role",
+    )
+    .bind(guild_id)
+    .bind(user_id)
+    .bind(role)
+    .execute(&mut *tx)
+    .await?;
 
     tx.commit().await?;
     Ok(())
 }
 
-pub async fn db_remove_user(pool: &sqlx::PgPool, user_id: i64) -> Result<(), Error> {
+pub async fn db_remove_user(pool: &sqlx::PgPool, guild_id: i64, user_id: i64) -> Result<(), Error> {
     let mut tx = pool.begin().await?;
 
-    sqlx::query("DELETE FROM users WHERE id = $1")
+    sqlx::query("DELETE FROM users WHERE guild_id = $1 AND id = $2")
+        .bind(guild_id)
         .bind(user_id)
         .execute(&mut *tx)
         .await?;
@@ -50,12 +166,18 @@ pub async fn db_remove_user(pool: &sqlx::PgPool, user_id: i64) -> Result<(), Err
     Ok(())
 }
 
-// Fetches the User's permission; Returns none if user isn't in Database
-pub async fn db_user_exists(pool: &sqlx::PgPool, user_id: i64) -> Result<bool, Error> {
-    let result: Option<User> = sqlx::query_as("SELECT permission FROM users WHERE id = $1")
-        .bind(user_id)
-        .fetch_optional(pool)
-        .await?;
+// Checks whether the user was added to the guild
+pub async fn db_user_exists(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    user_id: i64,
+) -> Result<bool, Error> {
+    let result: Option<(String,)> =
+        sqlx::query_as("SELECT role FROM users WHERE guild_id = $1 AND id = $2")
+            .bind(guild_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
 
     match result {
         Some(_) => Ok(true),
@@ -63,18 +185,813 @@ pub async fn db_user_exists(pool: &sqlx::PgPool, user_id: i64) -> Result<bool, E
     }
 }
 
-// Fetches the User's permission; Returns none if user isn't in Database
-pub async fn db_get_user_permission(
+// Fetches the highest role of the user, whether added directly or through their Discord roles;
+// Returns none if the user has neither
+pub async fn db_get_user_role(
     pool: &sqlx::PgPool,
+    guild_id: i64,
     user_id: i64,
-) -> Result<Option<i16>, Error> {
-    let result: Option<User> = sqlx::query_as("SELECT permission FROM users WHERE id = $1")
-        .bind(user_id)
-        .fetch_optional(pool)
+    discord_roles: &[i64],
+) -> Result<Option<Role>, Error> {
+    let result: Option<(String, i16, Vec<String>)> = sqlx::query_as(
+        "SELECT r.name, r.level, ARRAY_REMOVE(ARRAY_AGG(c.capability), NULL)
+        FROM roles r
+        LEFT JOIN role_capabilities c ON c.role = r.name
+        WHERE r.name IN (
+            SELECT role FROM users WHERE guild_id = $1 AND id = $2
+            UNION
+            SELECT role FROM role_grants WHERE guild_id = $1 AND discord_role_id = ANY($3)
+        )
+        GROUP BY r.name, r.level
+        ORDER BY r.level DESC
+        LIMIT 1",
+    )
+    .bind(guild_id)
+    .bind(user_id)
+    .bind(discord_roles)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(result.map(|(name, level, capabilities)| Role::new(name, level, capabilities)))
+}
+
+// Fetches every role, ordered from lowest to highest level
+pub async fn db_get_roles(pool: &sqlx::PgPool) -> Result<Vec<Role>, Error> {
+    let result: Vec<(String, i16, Vec<String>)> = sqlx::query_as(
+        "SELECT r.name, r.level, ARRAY_REMOVE(ARRAY_AGG(c.capability), NULL)
+        FROM roles r
+        LEFT JOIN role_capabilities c ON c.role = r.name
+        GROUP BY r.name, r.level
+        ORDER BY r.level",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(result
+        .into_iter()
+        .map(|(name, level, capabilities)| Role::new(name, level, capabilities))
+        .collect())
+}
+
+// Gives every member of the Discord role a role; Replaces the previous grant if there is one
+pub async fn db_add_role_grant(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    discord_role_id: i64,
+    role: &str,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO role_grants (guild_id, discord_role_id, role) VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id, discord_role_id) DO UPDATE SET role = EXCLUDED.role",
+    )
+    .bind(guild_id)
+    .bind(discord_role_id)
+    .bind(role)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Returns whether there was a grant to remove
+pub async fn db_remove_role_grant(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    discord_role_id: i64,
+) -> Result<bool, Error> {
+    let result =
+        sqlx::query("DELETE FROM role_grants WHERE guild_id = $1 AND discord_role_id = $2")
+            .bind(guild_id)
+            .bind(discord_role_id)
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Fetches the guild's grants as (Discord role, role name), highest level first
+pub async fn db_get_role_grants(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+) -> Result<Vec<(i64, String)>, Error> {
+    let result = sqlx::query_as(
+        "SELECT g.discord_role_id, g.role
+        FROM role_grants g
+        JOIN roles r ON r.name = g.role
+        WHERE g.guild_id = $1
+        ORDER BY r.level DESC",
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(result)
+}
+
+// Saves the encrypted token of a guild; Replaces the previous token if there is one
+pub async fn db_save_token(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    owner_id: Option<i64>,
+    token: &[u8],
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO spotify_token (guild_id, owner_id, token, updated_at) VALUES ($1, $2, $3, NOW())
+        ON CONFLICT (guild_id) DO UPDATE
+        SET owner_id = COALESCE(EXCLUDED.owner_id, spotify_token.owner_id), token = EXCLUDED.token, updated_at = NOW()",
+    )
+    .bind(guild_id)
+    .bind(owner_id)
+    .bind(token)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Fetches every guild's encrypted token along with who authenticated it
+pub async fn db_get_tokens(pool: &sqlx::PgPool) -> Result<Vec<(i64, Option<i64>, Vec<u8>)>, Error> {
+    let result = sqlx::query_as("SELECT guild_id, owner_id, token FROM spotify_token")
+        .fetch_all(pool)
         .await?;
 
-    match result {
-        Some(v) => Ok(Some(v.permission.unwrap())),
-        None => Ok(None),
+    Ok(result)
+}
+
+// Fetches who authenticated the guild's Spotify account
+pub async fn db_get_token_owner(pool: &sqlx::PgPool, guild_id: i64) -> Result<Option<i64>, Error> {
+    let result: Option<(Option<i64>,)> =
+        sqlx::query_as("SELECT owner_id FROM spotify_token WHERE guild_id = $1")
+            .bind(guild_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(result.and_then(|v| v.0))
+}
+
+// Assigns rows created before multi-guild support to a guild;
+// Rows the guild already has of its own are kept over the legacy ones
+pub async fn db_adopt_legacy_rows(pool: &sqlx::PgPool, guild_id: i64) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "DELETE FROM users WHERE guild_id = 0
+        AND id IN (SELECT id FROM users WHERE guild_id = $1)",
+    )
+    .bind(guild_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("UPDATE users SET guild_id = $1 WHERE guild_id = 0")
+        .bind(guild_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "DELETE FROM spotify_token WHERE guild_id = 0
+        AND EXISTS (SELECT 1 FROM spotify_token WHERE guild_id = $1)",
+    )
+    .bind(guild_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("UPDATE spotify_token SET guild_id = $1 WHERE guild_id = 0")
+        .bind(guild_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Fetches the guild's settings; Returns the defaults if they were never changed
+pub async fn db_get_settings(pool: &sqlx::PgPool, guild_id: i64) -> Result<GuildSettings, Error> {
+    let result: Option<GuildSettings> =
+        sqlx::query_as("SELECT * FROM guild_settings WHERE guild_id = $1")
+            .bind(guild_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(result.unwrap_or_default())
+}
+
+pub async fn db_save_settings(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    settings: &GuildSettings,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO guild_settings
+        (guild_id, vote_skip, vote_threshold, vote_percentage, vote_timeout, vote_voice_only,
+        collection_limit, preferred_device, frozen, frozen_until, allow_explicit, max_duration,
+        allow_podcasts)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        ON CONFLICT (guild_id) DO UPDATE SET
+        vote_skip = EXCLUDED.vote_skip,
+        vote_threshold = EXCLUDED.vote_threshold,
+        vote_percentage = EXCLUDED.vote_percentage,
+        vote_timeout = EXCLUDED.vote_timeout,
+        vote_voice_only = EXCLUDED.vote_voice_only,
+        collection_limit = EXCLUDED.collection_limit,
+        preferred_device = EXCLUDED.preferred_device,
+        frozen = EXCLUDED.frozen,
+        frozen_until = EXCLUDED.frozen_until,
+        allow_explicit = EXCLUDED.allow_explicit,
+        max_duration = EXCLUDED.max_duration,
+        allow_podcasts = EXCLUDED.allow_podcasts",
+    )
+    .bind(guild_id)
+    .bind(settings.vote_skip)
+    .bind(settings.vote_threshold)
+    .bind(settings.vote_percentage)
+    .bind(settings.vote_timeout)
+    .bind(settings.vote_voice_only)
+    .bind(settings.collection_limit)
+    .bind(&settings.preferred_device)
+    .bind(settings.frozen)
+    .bind(settings.frozen_until)
+    .bind(settings.allow_explicit)
+    .bind(settings.max_duration)
+    .bind(settings.allow_podcasts)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Records a song being queued through the bot; Waiting if it's held in the bot's queue
+pub async fn db_add_play(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    item: &StandardItem<'_>,
+    requester: i64,
+    waiting: bool,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO plays (guild_id, track_id, name, artists, url, requester, waiting)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(guild_id)
+    .bind(item.uri())
+    .bind(&item.name)
+    .bind(&item.artists)
+    .bind(&item.url)
+    .bind(requester)
+    .bind(waiting)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Records a waiting request being sent to Spotify's queue
+pub async fn db_play_released(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    item: &StandardItem<'_>,
+    requester: i64,
+) -> Result<(), Error> {
+    sqlx::query(
+        "UPDATE plays SET waiting = FALSE WHERE id = (
+            SELECT id FROM plays
+            WHERE guild_id = $1 AND track_id = $2 AND requester = $3 AND waiting
+            ORDER BY queued_at LIMIT 1
+        )",
+    )
+    .bind(guild_id)
+    .bind(item.uri())
+    .bind(requester)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Removes the requests that were still waiting before a restart, so they aren't counted; Returns how many
+pub async fn db_remove_waiting_plays(pool: &sqlx::PgPool) -> Result<u64, Error> {
+    let result = sqlx::query("DELETE FROM plays WHERE waiting AND started_at IS NULL")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Removes a request that was taken out of the queue before it was played, so it isn't counted
+pub async fn db_remove_play(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    item: &StandardItem<'_>,
+    requester: i64,
+) -> Result<(), Error> {
+    sqlx::query(
+        "DELETE FROM plays WHERE id = (
+            SELECT id FROM plays
+            WHERE guild_id = $1 AND track_id = $2 AND requester = $3 AND started_at IS NULL
+            ORDER BY queued_at DESC LIMIT 1
+        )",
+    )
+    .bind(guild_id)
+    .bind(item.uri())
+    .bind(requester)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Records a song starting; Whatever was playing before finished unless it was skipped.
+// Uses the oldest matching request if it was queued through the bot
+pub async fn db_play_started(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    item: &StandardItem<'_>,
+) -> Result<(), Error> {
+    let uri = item.uri();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE plays SET ended = 'finished'
+        WHERE guild_id = $1 AND started_at IS NOT NULL AND ended IS NULL AND track_id <> $2",
+    )
+    .bind(guild_id)
+    .bind(&uri)
+    .execute(&mut *tx)
+    .await?;
+
+    // Already known as playing, such as after a restart
+    let playing: Option<(i64,)> = sqlx::query_as(
+        "SELECT id FROM plays
+        WHERE guild_id = $1 AND track_id = $2 AND started_at IS NOT NULL AND ended IS NULL",
+    )
+    .bind(guild_id)
+    .bind(&uri)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if playing.is_some() {
+        tx.commit().await?;
+        return Ok(());
     }
+
+    let requested = sqlx::query(
+        "UPDATE plays SET started_at = NOW()
+        WHERE id = (
+            SELECT id FROM plays
+            WHERE guild_id = $1 AND track_id = $2 AND started_at IS NULL
+            ORDER BY queued_at
+            LIMIT 1
+        )",
+    )
+    .bind(guild_id)
+    .bind(&uri)
+    .execute(&mut *tx)
+    .await?;
+
+    if requested.rows_affected() == 0 {
+        sqlx::query(
+            "INSERT INTO plays (guild_id, track_id, name, artists, url, started_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())",
+        )
+        .bind(guild_id)
+        .bind(&uri)
+        .bind(&item.name)
+        .bind(&item.artists)
+        .bind(&item.url)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Records the playing song being skipped
+pub async fn db_play_skipped(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    item: &StandardItem<'_>,
+) -> Result<(), Error> {
+    sqlx::query(
+        "UPDATE plays SET ended = 'skipped'
+        WHERE guild_id = $1 AND track_id = $2 AND started_at IS NOT NULL AND ended IS NULL",
+    )
+    .bind(guild_id)
+    .bind(item.uri())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Fetches who requested each of the songs that haven't started yet
+pub async fn db_get_requesters(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    uris: &[String],
+) -> Result<HashMap<String, i64>, Error> {
+    let result: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT DISTINCT ON (track_id) track_id, requester FROM plays
+        WHERE guild_id = $1 AND track_id = ANY($2) AND started_at IS NULL AND requester IS NOT NULL
+        ORDER BY track_id, queued_at",
+    )
+    .bind(guild_id)
+    .bind(uris)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(result.into_iter().collect())
+}
+
+// Fetches the most recently played songs, newest first
+pub async fn db_get_history(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    limit: i64,
+) -> Result<Vec<Play>, Error> {
+    let result = sqlx::query_as(
+        "SELECT name, artists, url, requester, started_at, ended FROM plays
+        WHERE guild_id = $1 AND started_at IS NOT NULL
+        ORDER BY started_at DESC
+        LIMIT $2",
+    )
+    .bind(guild_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(result)
+}
+
+// Fetches the quota of the user, falling back to the quota of their role; Returns none if unlimited
+pub async fn db_get_quota(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    user_id: i64,
+    role: Option<&str>,
+) -> Result<Option<Quota>, Error> {
+    let result: Option<Quota> = sqlx::query_as(
+        "SELECT max_requests, period FROM user_quotas WHERE guild_id = $1 AND user_id = $2",
+    )
+    .bind(guild_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    if result.is_some() {
+        return Ok(result);
+    }
+
+    let role = match role {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let result = sqlx::query_as(
+        "SELECT max_requests, period FROM role_quotas WHERE guild_id = $1 AND role = $2",
+    )
+    .bind(guild_id)
+    .bind(role)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(result)
+}
+
+// Counts the user's requests in the last period, and when the oldest of them was made
+pub async fn db_count_requests(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    user_id: i64,
+    period: i32,
+) -> Result<(i64, Option<DateTime<Utc>>), Error> {
+    let result = sqlx::query_as(
+        "SELECT COUNT(*), MIN(queued_at) FROM plays
+        WHERE guild_id = $1 AND requester = $2
+        AND queued_at > NOW() - make_interval(secs => $3)",
+    )
+    .bind(guild_id)
+    .bind(user_id)
+    .bind(period as f64)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(result)
+}
+
+pub async fn db_set_role_quota(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    role: &str,
+    quota: &Quota,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO role_quotas (guild_id, role, max_requests, period) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (guild_id, role) DO UPDATE
+        SET max_requests = EXCLUDED.max_requests, period = EXCLUDED.period",
+    )
+    .bind(guild_id)
+    .bind(role)
+    .bind(quota.max_requests)
+    .bind(quota.period)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Returns whether there was a quota to remove
+pub async fn db_remove_role_quota(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    role: &str,
+) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM role_quotas WHERE guild_id = $1 AND role = $2")
+        .bind(guild_id)
+        .bind(role)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn db_set_user_quota(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    user_id: i64,
+    quota: &Quota,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO user_quotas (guild_id, user_id, max_requests, period) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (guild_id, user_id) DO UPDATE
+        SET max_requests = EXCLUDED.max_requests, period = EXCLUDED.period",
+    )
+    .bind(guild_id)
+    .bind(user_id)
+    .bind(quota.max_requests)
+    .bind(quota.period)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Returns whether there was a quota to remove
+pub async fn db_remove_user_quota(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    user_id: i64,
+) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM user_quotas WHERE guild_id = $1 AND user_id = $2")
+        .bind(guild_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn db_get_freeze_schedules(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+) -> Result<Vec<FreezeSchedule>, Error> {
+    let result: Vec<FreezeSchedule> = sqlx::query_as(
+        "SELECT id, days, starts, ends, timezone FROM freeze_schedules
+        WHERE guild_id = $1 ORDER BY id",
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(result)
+}
+
+// Returns whether /freeze is on and hasn't expired, with the guild's schedules, in one query
+pub async fn db_get_freeze_state(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+) -> Result<(bool, Vec<FreezeSchedule>), Error> {
+    // A row for every schedule, or a single row without one
+    #[allow(clippy::type_complexity)]
+    let rows: Vec<(
+        bool,
+        Option<i64>,
+        Option<i16>,
+        Option<NaiveTime>,
+        Option<NaiveTime>,
+        Option<String>,
+    )> = sqlx::query_as(
+        "SELECT
+            COALESCE(s.frozen AND (s.frozen_until IS NULL OR s.frozen_until > NOW()), FALSE),
+            f.id, f.days, f.starts, f.ends, f.timezone
+        FROM (SELECT $1::BIGINT AS guild_id) g
+        LEFT JOIN guild_settings s ON s.guild_id = g.guild_id
+        LEFT JOIN freeze_schedules f ON f.guild_id = g.guild_id
+        ORDER BY f.id",
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    let frozen = rows.first().is_some_and(|v| v.0);
+    let schedules = rows
+        .into_iter()
+        .filter_map(|(_, id, days, starts, ends, timezone)| {
+            Some(FreezeSchedule {
+                id: id?,
+                days: days?,
+                starts: starts?,
+                ends: ends?,
+                timezone: timezone?,
+            })
+        })
+        .collect();
+
+    Ok((frozen, schedules))
+}
+
+// Returns the id of the new schedule
+pub async fn db_add_freeze_schedule(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    days: i16,
+    starts: NaiveTime,
+    ends: NaiveTime,
+    timezone: &str,
+) -> Result<i64, Error> {
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO freeze_schedules (guild_id, days, starts, ends, timezone)
+        VALUES ($1, $2, $3, $4, $5) RETURNING id",
+    )
+    .bind(guild_id)
+    .bind(days)
+    .bind(starts)
+    .bind(ends)
+    .bind(timezone)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+// Returns whether the schedule existed
+pub async fn db_remove_freeze_schedule(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    id: i64,
+) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM freeze_schedules WHERE guild_id = $1 AND id = $2")
+        .bind(guild_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn db_get_blocklist(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+) -> Result<Vec<BlockedItem>, Error> {
+    let result: Vec<BlockedItem> = sqlx::query_as(
+        "SELECT kind, item_id, name FROM blocklist WHERE guild_id = $1 ORDER BY kind, name",
+    )
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(result)
+}
+
+pub async fn db_add_block(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    kind: &str,
+    item_id: &str,
+    name: &str,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO blocklist (guild_id, kind, item_id, name) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (guild_id, kind, item_id) DO UPDATE SET name = EXCLUDED.name",
+    )
+    .bind(guild_id)
+    .bind(kind)
+    .bind(item_id)
+    .bind(name)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Returns whether it was blocked
+pub async fn db_remove_block(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    kind: &str,
+    item_id: &str,
+) -> Result<bool, Error> {
+    let result =
+        sqlx::query("DELETE FROM blocklist WHERE guild_id = $1 AND kind = $2 AND item_id = $3")
+            .bind(guild_id)
+            .bind(kind)
+            .bind(item_id)
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn db_add_audit(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    actor_id: i64,
+    action: &str,
+    target: Option<&str>,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO audit_log (guild_id, actor_id, action, target, old_value, new_value)
+        VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(guild_id)
+    .bind(actor_id)
+    .bind(action)
+    .bind(target)
+    .bind(old_value)
+    .bind(new_value)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Fetches the newest entries first; Each filter is skipped when none
+pub async fn db_get_audit(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    actor_id: Option<i64>,
+    action: Option<&str>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<AuditEntry>, Error> {
+    let result: Vec<AuditEntry> = sqlx::query_as(
+        "SELECT actor_id, action, target, old_value, new_value, created_at FROM audit_log
+        WHERE guild_id = $1
+        AND ($2::BIGINT IS NULL OR actor_id = $2)
+        AND ($3::TEXT IS NULL OR action = $3)
+        AND ($4::TIMESTAMPTZ IS NULL OR created_at >= $4)
+        AND ($5::TIMESTAMPTZ IS NULL OR created_at < $5)
+        ORDER BY created_at DESC, id DESC
+        LIMIT $6",
+    )
+    .bind(guild_id)
+    .bind(actor_id)
+    .bind(action)
+    .bind(since)
+    .bind(until)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(result)
+}
+
+// Fetches where the guild's events are posted; Returns the defaults if they were never changed
+pub async fn db_get_log_settings(pool: &sqlx::PgPool, guild_id: i64) -> Result<LogSettings, Error> {
+    let result: Option<LogSettings> =
+        sqlx::query_as("SELECT * FROM log_settings WHERE guild_id = $1")
+            .bind(guild_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(result.unwrap_or_default())
+}
+
+pub async fn db_save_log_settings(
+    pool: &sqlx::PgPool,
+    guild_id: i64,
+    settings: &LogSettings,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO log_settings
+        (guild_id, channel_id, log_queue, log_skips, log_previous, log_freeze, log_permissions,
+        log_authentication)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (guild_id) DO UPDATE SET
+        channel_id = EXCLUDED.channel_id,
+        log_queue = EXCLUDED.log_queue,
+        log_skips = EXCLUDED.log_skips,
+        log_previous = EXCLUDED.log_previous,
+        log_freeze = EXCLUDED.log_freeze,
+        log_permissions = EXCLUDED.log_permissions,
+        log_authentication = EXCLUDED.log_authentication",
+    )
+    .bind(guild_id)
+    .bind(settings.channel_id)
+    .bind(settings.log_queue)
+    .bind(settings.log_skips)
+    .bind(settings.log_previous)
+    .bind(settings.log_freeze)
+    .bind(settings.log_permissions)
+    .bind(settings.log_authentication)
+    .execute(pool)
+    .await?;
+
+    Ok(())
 }
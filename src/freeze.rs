@@ -0,0 +1,96 @@
+use chrono::{DateTime, Datelike, NaiveTime, TimeDelta, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use poise::serenity_prelude::GuildId;
+
+use crate::database::{db_get_freeze_state, FreezeSchedule};
+use crate::Error;
+
+/// Every day of the week, as stored in `freeze_schedules.days`
+const EVERY_DAY: i16 = 0b111_1111;
+const WEEKDAYS: i16 = 0b001_1111;
+const WEEKENDS: i16 = 0b110_0000;
+
+/// Whether playback changes are frozen, either by /freeze or a schedule
+pub async fn is_frozen(pool: &sqlx::PgPool, guild_id: GuildId) -> Result<bool, Error> {
+    // Checked on most commands, so both are loaded at once
+    let (frozen, schedules) = db_get_freeze_state(pool, guild_id.get() as i64).await?;
+    let now = Utc::now();
+    Ok(frozen || schedules.iter().any(|v| in_window(v, now)))
+}
+
+/// Whether the time is inside the schedule's window, in the schedule's timezone
+pub fn in_window(schedule: &FreezeSchedule, now: DateTime<Utc>) -> bool {
+    let timezone: Tz = match schedule.timezone.parse() {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    let local = now.with_timezone(&timezone);
+    let time = local.time();
+    let today = has_day(schedule.days, local.weekday());
+
+    if schedule.starts < schedule.ends {
+        return today && schedule.starts <= time && time < schedule.ends;
+    }
+
+    // The window crosses midnight, so it may have started yesterday
+    let yesterday = has_day(schedule.days, local.weekday().pred());
+    (today && time >= schedule.starts) || (yesterday && time < schedule.ends)
+}
+
+fn has_day(days: i16, day: Weekday) -> bool {
+    days & (1 << day.num_days_from_monday()) != 0
+}
+
+/// Parses "daily", "weekdays", "weekends" or a list of days like "mon,wed,fri"
+pub fn parse_days(input: &str) -> Option<i16> {
+    match input.trim().to_lowercase().as_str() {
+        "daily" | "everyday" | "every day" => return Some(EVERY_DAY),
+        "weekdays" => return Some(WEEKDAYS),
+        "weekends" => return Some(WEEKENDS),
+        _ => (),
+    }
+
+    let mut days = 0;
+    for day in input.split(',') {
+        let day: Weekday = day.trim().parse().ok()?;
+        days |= 1 << day.num_days_from_monday();
+    }
+
+    Some(days)
+}
+
+/// Reverses `parse_days`
+pub fn format_days(days: i16) -> String {
+    match days {
+        EVERY_DAY => "Every day".to_string(),
+        WEEKDAYS => "Weekdays".to_string(),
+        WEEKENDS => "Weekends".to_string(),
+        _ => (0..7)
+            .filter_map(|v| Weekday::try_from(v as u8).ok())
+            .filter(|v| has_day(days, *v))
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Parses a time of day like "9:00" or "17:30"
+pub fn parse_time(input: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(input.trim(), "%H:%M").ok()
+}
+
+/// Returns the next time the clock in the timezone reads the time
+pub fn next_occurrence(time: NaiveTime, timezone: Tz, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let today = now.with_timezone(&timezone).date_naive();
+
+    // Times skipped by daylight saving don't exist, so try the next day
+    (0..=2)
+        .filter_map(|offset| {
+            let date = today + TimeDelta::days(offset);
+            timezone
+                .from_local_datetime(&date.and_time(time))
+                .earliest()
+        })
+        .map(|v| v.with_timezone(&Utc))
+        .find(|v| *v > now)
+}
@@ -1,15 +1,65 @@
+pub mod access;
+pub mod audit;
+pub mod blocklist;
+pub mod callback;
 pub mod commands;
+pub mod crypto;
 pub mod database;
+pub mod freeze;
+pub mod log_channel;
+pub mod nowplaying;
+pub mod panel;
+pub mod permissions;
+pub mod policy;
+pub mod poller;
+pub mod requests;
+pub mod service;
 pub mod spotify;
 
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
+
+use callback::PendingAuths;
+use crypto::TokenCipher;
+use nowplaying::LiveMessages;
+use poise::serenity_prelude::{GuildId, UserId};
+use requests::RequestQueue;
 use rspotify::AuthCodePkceSpotify;
+use spotify::CachedSearch;
 use tokio::sync::RwLock;
 
 // User data, which is stored and accessible in all command invocations
 pub struct Data {
-    pub spotify: RwLock<Option<AuthCodePkceSpotify>>,
+    pub spotify: Arc<RwLock<HashMap<GuildId, AuthCodePkceSpotify>>>,
     pub pool: sqlx::PgPool,
-    pub freeze: RwLock<bool>,
+    pub cipher: TokenCipher,
+    /// Songs waiting to be sent to each guild's Spotify queue
+    pub requests: Arc<RwLock<HashMap<GuildId, RequestQueue>>>,
+    /// Guilds with a vote to skip running
+    pub skip_votes: RwLock<HashSet<GuildId>>,
+    /// Recent /play search suggestions, by guild and query
+    pub search_cache: RwLock<HashMap<(GuildId, String), CachedSearch>>,
+    /// When each user last typed in /play, to wait until they stop
+    pub typing: RwLock<HashMap<UserId, Instant>>,
+    /// The /nowplaying message being updated in each channel
+    pub live_messages: LiveMessages,
+    /// Runs of /authenticate waiting for Spotify to redirect back
+    pub pending_auth: PendingAuths,
+}
+
+impl Data {
+    /// Whether playback changes are frozen in the guild
+    pub async fn is_frozen(&self, guild_id: GuildId) -> Result<bool, Error> {
+        freeze::is_frozen(&self.pool, guild_id).await
+    }
+
+    /// Returns the guild's Spotify client, if it authenticated
+    pub async fn client(&self, guild_id: GuildId) -> Option<AuthCodePkceSpotify> {
+        self.spotify.read().await.get(&guild_id).cloned()
+    }
 }
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -22,6 +72,42 @@ pub fn format_delta(time: chrono::TimeDelta) -> String {
     format!("{:02}:{:02}", minutes, seconds)
 }
 
-pub async fn is_frozen(ctx: Context<'_>) -> bool {
-    *ctx.data().freeze.read().await
+/// Formats a period in seconds as minutes, hours or days
+pub fn format_period(seconds: i32) -> String {
+    let minutes = seconds / 60;
+    match minutes {
+        0..=1 => "minute".to_string(),
+        2..=59 => format!("{minutes} minutes"),
+        60 => "hour".to_string(),
+        61..=1439 if minutes % 60 == 0 => format!("{} hours", minutes / 60),
+        1440 => "day".to_string(),
+        _ if minutes % 1440 == 0 => format!("{} days", minutes / 1440),
+        _ => format!("{minutes} minutes"),
+    }
+}
+
+/// Reverses `format_delta`; Accepts minutes:seconds, hours:minutes:seconds or just seconds
+pub fn parse_delta(time: &str) -> Option<chrono::TimeDelta> {
+    let parts = time.trim().split(':').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        return None;
+    }
+
+    let mut total_seconds: i64 = 0;
+    for (index, part) in parts.iter().enumerate() {
+        let value = part.parse::<u32>().ok()? as i64;
+        // Everything but the largest unit is below 60
+        if index > 0 && value >= 60 {
+            return None;
+        }
+        total_seconds = total_seconds * 60 + value;
+    }
+
+    Some(chrono::TimeDelta::seconds(total_seconds))
+}
+
+/// Returns the guild the command was run in; Commands are guild only
+pub fn guild_id(ctx: Context<'_>) -> Result<GuildId, Error> {
+    ctx.guild_id()
+        .ok_or_else(|| "This command can only be used in a server".into())
 }
@@ -0,0 +1,120 @@
+use poise::serenity_prelude::{
+    ChannelId, Colour, CreateEmbed, CreateEmbedAuthor, CreateMessage, GuildId, Http, Timestamp,
+    UserId,
+};
+use poise::ChoiceParameter;
+use tracing::warn;
+
+use crate::audit::{AuditAction, Event};
+use crate::database::{db_get_log_settings, LogSettings};
+use crate::Error;
+
+/// Events that can be posted to the log channel; Each is turned on and off by itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum LogEvent {
+    Queue,
+    Skip,
+    Previous,
+    Freeze,
+    Permissions,
+    Authentication,
+}
+
+impl LogEvent {
+    pub const ALL: [LogEvent; 6] = [
+        LogEvent::Queue,
+        LogEvent::Skip,
+        LogEvent::Previous,
+        LogEvent::Freeze,
+        LogEvent::Permissions,
+        LogEvent::Authentication,
+    ];
+
+    /// Which event an audited action is; None if it's never posted
+    pub fn of(action: AuditAction) -> Option<Self> {
+        match action {
+            AuditAction::Play => Some(LogEvent::Queue),
+            AuditAction::Skip => Some(LogEvent::Skip),
+            AuditAction::Previous => Some(LogEvent::Previous),
+            AuditAction::Freeze | AuditAction::FreezeSchedule => Some(LogEvent::Freeze),
+            AuditAction::AddUser
+            | AuditAction::RemoveUser
+            | AuditAction::GrantRole
+            | AuditAction::RevokeRole => Some(LogEvent::Permissions),
+            AuditAction::Authenticate => Some(LogEvent::Authentication),
+            _ => None,
+        }
+    }
+
+    pub fn is_enabled(&self, settings: &LogSettings) -> bool {
+        match self {
+            LogEvent::Queue => settings.log_queue,
+            LogEvent::Skip => settings.log_skips,
+            LogEvent::Previous => settings.log_previous,
+            LogEvent::Freeze => settings.log_freeze,
+            LogEvent::Permissions => settings.log_permissions,
+            LogEvent::Authentication => settings.log_authentication,
+        }
+    }
+
+    pub fn set_enabled(&self, settings: &mut LogSettings, enabled: bool) {
+        let toggle = match self {
+            LogEvent::Queue => &mut settings.log_queue,
+            LogEvent::Skip => &mut settings.log_skips,
+            LogEvent::Previous => &mut settings.log_previous,
+            LogEvent::Freeze => &mut settings.log_freeze,
+            LogEvent::Permissions => &mut settings.log_permissions,
+            LogEvent::Authentication => &mut settings.log_authentication,
+        };
+        *toggle = enabled;
+    }
+
+    fn colour(&self) -> Colour {
+        match self {
+            LogEvent::Queue => Colour::DARK_GREEN,
+            LogEvent::Skip | LogEvent::Previous => Colour::GOLD,
+            LogEvent::Freeze => Colour::BLUE,
+            LogEvent::Permissions => Colour::PURPLE,
+            LogEvent::Authentication => Colour::TEAL,
+        }
+    }
+}
+
+/// A single line embed of who did what
+pub fn embed(kind: LogEvent, user_id: UserId, event: &Event) -> CreateEmbed {
+    CreateEmbed::new()
+        .colour(kind.colour())
+        .author(CreateEmbedAuthor::new(event.action.name()))
+        .description(
+            format!("<@{user_id}> {}", event.describe())
+                .trim_end()
+                .to_string(),
+        )
+        .timestamp(Timestamp::now())
+}
+
+/// Posts the event to the guild's log channel, if it has one and posts that event.
+/// Posting can fail when the bot can't see the channel, which is only logged
+pub async fn post(
+    http: &Http,
+    pool: &sqlx::PgPool,
+    guild_id: GuildId,
+    user_id: UserId,
+    event: &Event,
+) -> Result<(), Error> {
+    let kind = match LogEvent::of(event.action) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let settings = db_get_log_settings(pool, guild_id.get() as i64).await?;
+    let channel = match settings.channel_id {
+        Some(v) if kind.is_enabled(&settings) => ChannelId::new(v as u64),
+        _ => return Ok(()),
+    };
+
+    let message = CreateMessage::new().embed(embed(kind, user_id, event));
+    if let Err(err) = channel.send_message(http, message).await {
+        warn!("Failed to post to the log channel of {guild_id}: {err}");
+    }
+    Ok(())
+}
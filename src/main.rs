@@ -1,21 +1,60 @@
-#[deny(clippy::all)]
-use std::env;
+#![deny(clippy::all)]
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    net::SocketAddr,
+    sync::Arc,
+};
 
 use anyhow::Context as _;
 use delegatify::{
-    commands::{add_user, authenticate, current, freeze, next, play, previous, queue, remove_user},
-    database, Data,
+    callback::{self, CallbackState, PendingAuths},
+    commands::{
+        add_user, audit_log, authenticate, block, blocklist, clear_queue, current, device, devices,
+        freeze, grant_role, history, log_settings, move_request, next, nowplaying, panel, pause,
+        play, previous, queue, queue_settings, quota, remove_quota, remove_user, repeat, resume,
+        revoke_role, role_grants, seek, set_quota, shuffle, unblock, unqueue, volume,
+        vote_settings,
+    },
+    crypto::TokenCipher,
+    database, panel, poller, spotify, Data, Error,
 };
-use poise::serenity_prelude::{ClientBuilder, GatewayIntents};
-use shuttle_runtime::SecretStore;
-use shuttle_serenity::ShuttleSerenity;
+use poise::serenity_prelude::{self as serenity, ClientBuilder, GatewayIntents};
+use shuttle_runtime::{CustomError, SecretStore};
 use tokio::sync::RwLock;
+use tracing::info;
+
+/// The bot, and the server Spotify redirects to after /authenticate
+struct BotService {
+    client: serenity::Client,
+    callback: axum::Router,
+    /// Where the callback server listens instead of the address Shuttle gives
+    callback_address: Option<SocketAddr>,
+}
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for BotService {
+    async fn bind(mut self, addr: SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        let addr = self.callback_address.unwrap_or(addr);
+        let server = axum::Server::try_bind(&addr)
+            .map_err(CustomError::new)?
+            .serve(self.callback.into_make_service());
+        info!("Listening for Spotify callbacks on {addr}");
+
+        // Either stopping stops both
+        tokio::select! {
+            result = self.client.start_autosharded() => result.map_err(CustomError::new)?,
+            result = server => result.map_err(CustomError::new)?,
+        }
+        Ok(())
+    }
+}
 
 #[shuttle_runtime::main]
 async fn main(
     #[shuttle_runtime::Secrets] secret_store: SecretStore,
     #[shuttle_shared_db::Postgres] pool: sqlx::PgPool,
-) -> ShuttleSerenity {
+) -> Result<BotService, shuttle_runtime::Error> {
     // Discord Secrets
     let discord_token = secret_store
         .get("DISCORD_TOKEN")
@@ -32,49 +71,173 @@ async fn main(
         .get("SPOTIFY_REDIRECT_URI")
         .context("'SPOTIFY_REDIRECT_URI' was not found")?;
 
+    // Key used to encrypt the Spotify token at rest
+    let encryption_key = secret_store
+        .get("TOKEN_ENCRYPTION_KEY")
+        .context("'TOKEN_ENCRYPTION_KEY' was not found")?;
+    let cipher = TokenCipher::new(&encryption_key);
+
+    // Guild that owns users and tokens from before multi-guild support
+    let legacy_guild = secret_store
+        .get("LEGACY_GUILD_ID")
+        .map(|v| v.parse::<i64>())
+        .transpose()
+        .context("'LEGACY_GUILD_ID' is not a valid id")?;
+
+    // Address to serve the callback on instead of the one Shuttle gives
+    let callback_address = secret_store
+        .get("CALLBACK_ADDRESS")
+        .map(|v| v.parse::<SocketAddr>())
+        .transpose()
+        .context("'CALLBACK_ADDRESS' is not a valid address")?;
+
     // set ENV variables for rspotify
     env::set_var("RSPOTIFY_CLIENT_ID", client_id.clone());
     env::set_var("RSPOTIFY_CLIENT_SECRET", client_secret.clone());
     env::set_var("RSPOTIFY_REDIRECT_URI", callback_url.clone());
+    if let Some(api_url) = secret_store.get("SPOTIFY_API_URL") {
+        env::set_var("SPOTIFY_API_URL", api_url);
+    }
 
     // Handle migrations
     database::migrate(&pool)
         .await
         .context("Failed to migrate Database")?;
+    if let Some(guild_id) = legacy_guild {
+        database::db_adopt_legacy_rows(&pool, guild_id)
+            .await
+            .map_err(|err| anyhow::anyhow!(err))
+            .context("Failed to adopt legacy rows")?;
+    }
+
+    // Requests waiting in the bot's queue didn't survive the restart
+    let dropped = database::db_remove_waiting_plays(&pool)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))
+        .context("Failed to remove waiting requests")?;
+    if dropped > 0 {
+        info!("Dropped {dropped} requests that were waiting before the restart");
+    }
+
+    // Reconnect to Spotify for every guild that saved a token before the restart
+    let spotify = spotify::restore(&pool, &cipher)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))
+        .context("Failed to restore Spotify tokens")?;
+    info!("Restored Spotify tokens for {} guilds", spotify.len());
+    let spotify = Arc::new(RwLock::new(spotify));
+    let pending_auth = PendingAuths::default();
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             commands: vec![
                 // Playback
                 current(),
+                nowplaying(),
+                panel(),
                 queue(),
                 play(),
+                unqueue(),
+                move_request(),
+                clear_queue(),
                 previous(),
                 next(),
+                pause(),
+                resume(),
+                seek(),
+                volume(),
+                shuffle(),
+                repeat(),
+                devices(),
+                device(),
+                history(),
                 // Utilities
+                quota(),
+                set_quota(),
+                remove_quota(),
+                block(),
+                unblock(),
+                blocklist(),
+                vote_settings(),
+                queue_settings(),
+                log_settings(),
                 freeze(),
                 add_user(),
                 remove_user(),
+                grant_role(),
+                revoke_role(),
+                role_grants(),
+                audit_log(),
                 authenticate(),
             ],
+            event_handler: |ctx, event, framework, data| {
+                Box::pin(event_handler(ctx, event, framework, data))
+            },
             ..Default::default()
         })
-        .setup(|ctx, _ready, framework| {
-            Box::pin(async move {
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data {
-                    spotify: RwLock::new(None),
-                    pool,
-                    freeze: RwLock::new(false),
+        .setup({
+            let (pool, spotify, pending_auth) =
+                (pool.clone(), spotify.clone(), pending_auth.clone());
+            |ctx, _ready, framework| {
+                Box::pin(async move {
+                    poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+
+                    let requests = Arc::new(RwLock::new(HashMap::new()));
+
+                    // Record playback history and feed requests to Spotify in the background
+                    tokio::spawn(poller::run(pool.clone(), spotify.clone(), requests.clone()));
+
+                    Ok(Data {
+                        spotify,
+                        requests,
+                        pool,
+                        cipher,
+                        skip_votes: RwLock::new(HashSet::new()),
+                        search_cache: RwLock::new(HashMap::new()),
+                        typing: RwLock::new(HashMap::new()),
+                        live_messages: Arc::new(RwLock::new(HashMap::new())),
+                        pending_auth,
+                    })
                 })
-            })
+            }
         })
         .build();
 
     let client = ClientBuilder::new(discord_token, GatewayIntents::non_privileged())
         .framework(framework)
         .await
-        .map_err(shuttle_runtime::CustomError::new)?;
+        .map_err(CustomError::new)?;
+
+    let callback = callback::router(
+        &callback_url,
+        CallbackState {
+            pending: pending_auth,
+            spotify,
+            pool,
+            http: client.http.clone(),
+        },
+    );
+
+    Ok(BotService {
+        client,
+        callback,
+        callback_address,
+    })
+}
+
+/// Handles events outside of commands, like control panel buttons
+async fn event_handler(
+    ctx: &serenity::Context,
+    event: &serenity::FullEvent,
+    framework: poise::FrameworkContext<'_, Data, Error>,
+    data: &Data,
+) -> Result<(), Error> {
+    if let serenity::FullEvent::InteractionCreate {
+        interaction: serenity::Interaction::Component(interaction),
+    } = event
+    {
+        panel::handle(ctx, framework, data, interaction).await?;
+    }
 
-    Ok(client.into())
+    Ok(())
 }
@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::{ChannelId, CreateEmbed, EditMessage, GuildId, Http, MessageId};
+use rspotify::{prelude::OAuthClient, AuthCodePkceSpotify};
+use tokio::sync::RwLock;
+use tracing::debug;
+
+use crate::commands::{current_no_playback, current_playback};
+use crate::spotify::StandardItem;
+
+/// How often live messages are edited
+const UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+/// How long nothing can be playing before a live message stops updating
+const INACTIVE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The live message of every channel
+pub type LiveMessages = Arc<RwLock<HashMap<ChannelId, MessageId>>>;
+
+/// Keeps a /nowplaying message up to date until playback stops for a while,
+/// or another /nowplaying replaces it in the channel
+pub async fn run(
+    http: Arc<Http>,
+    spotify: Arc<RwLock<HashMap<GuildId, AuthCodePkceSpotify>>>,
+    live: LiveMessages,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) {
+    let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+    let mut last_active = Instant::now();
+    let mut last_state = None;
+
+    loop {
+        interval.tick().await;
+
+        if live.read().await.get(&channel_id) != Some(&message_id) {
+            return;
+        }
+        let client = match spotify.read().await.get(&guild_id) {
+            Some(v) => v.clone(),
+            None => break,
+        };
+
+        let playback = match client.current_playback(None, None::<Vec<_>>).await {
+            Ok(v) => v,
+            Err(err) => {
+                debug!("Failed to update now playing in {channel_id}: {err}");
+                continue;
+            }
+        };
+
+        // Only edit the message when something shown changed
+        let (embed, state) = match playback.as_ref().and_then(|v| Some((v, v.item.clone()?))) {
+            Some((playback, item)) => {
+                if playback.is_playing {
+                    last_active = Instant::now();
+                }
+                let state = (
+                    StandardItem::parse(item.clone()).uri(),
+                    playback.progress.map(|v| v.num_seconds()),
+                    playback.is_playing,
+                    playback.shuffle_state,
+                    playback.repeat_state,
+                );
+                let embed = current_playback(playback, item, CreateEmbed::new()).await;
+                (embed, Some(state))
+            }
+            None => (current_no_playback(CreateEmbed::new()).await, None),
+        };
+        if last_active.elapsed() >= INACTIVE_TIMEOUT {
+            break;
+        }
+        if state == last_state {
+            continue;
+        }
+        last_state = state;
+
+        if let Err(err) = channel_id
+            .edit_message(&http, message_id, EditMessage::new().embed(embed))
+            .await
+        {
+            // Most likely the message was deleted
+            debug!("Stopped updating now playing in {channel_id}: {err}");
+            break;
+        }
+    }
+
+    let mut live = live.write().await;
+    if live.get(&channel_id) == Some(&message_id) {
+        live.remove(&channel_id);
+    }
+}
@@ -0,0 +1,235 @@
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, ComponentInteraction, CreateActionRow, CreateButton,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    EditInteractionResponse,
+};
+use rspotify::{model::RepeatState, prelude::OAuthClient, AuthCodePkceSpotify};
+use tracing::info;
+
+use crate::access::{self, playback_denial, Actor};
+use crate::audit::{self, AuditAction, Event};
+use crate::commands::{current_no_playback, current_playback};
+use crate::database::db_get_settings;
+use crate::permissions::Capability;
+use crate::{log_channel, service, Data, Error};
+
+/// Starts the custom id of every control panel button; The ids never change,
+/// so panels posted before a restart keep working
+const PREFIX: &str = "panel:";
+/// How much the volume buttons change the volume by
+const VOLUME_STEP: u32 = 10;
+
+/// What a control panel button does
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    Previous,
+    Toggle,
+    Next,
+    Shuffle,
+    Repeat,
+    VolumeDown,
+    VolumeUp,
+}
+
+impl Action {
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Previous => "previous",
+            Action::Toggle => "toggle",
+            Action::Next => "next",
+            Action::Shuffle => "shuffle",
+            Action::Repeat => "repeat",
+            Action::VolumeDown => "volume_down",
+            Action::VolumeUp => "volume_up",
+        }
+    }
+
+    fn parse(custom_id: &str) -> Option<Self> {
+        match custom_id.strip_prefix(PREFIX)? {
+            "previous" => Some(Action::Previous),
+            "toggle" => Some(Action::Toggle),
+            "next" => Some(Action::Next),
+            "shuffle" => Some(Action::Shuffle),
+            "repeat" => Some(Action::Repeat),
+            "volume_down" => Some(Action::VolumeDown),
+            "volume_up" => Some(Action::VolumeUp),
+            _ => None,
+        }
+    }
+
+    fn capability(&self) -> Capability {
+        match self {
+            Action::Previous => Capability::Previous,
+            Action::Toggle => Capability::Pause,
+            Action::Next => Capability::Skip,
+            Action::Shuffle => Capability::Shuffle,
+            Action::Repeat => Capability::Repeat,
+            Action::VolumeDown | Action::VolumeUp => Capability::Volume,
+        }
+    }
+
+    fn button(&self, label: &str) -> CreateButton {
+        CreateButton::new(format!("{PREFIX}{}", self.name()))
+            .label(label)
+            .style(ButtonStyle::Secondary)
+    }
+}
+
+/// Buttons of the control panel
+pub fn components() -> Vec<CreateActionRow> {
+    vec![
+        CreateActionRow::Buttons(vec![
+            Action::Previous.button("⏮"),
+            Action::Toggle.button("⏯"),
+            Action::Next.button("⏭"),
+        ]),
+        CreateActionRow::Buttons(vec![
+            Action::Shuffle.button("🔀"),
+            Action::Repeat.button("🔁"),
+            Action::VolumeDown.button("🔉"),
+            Action::VolumeUp.button("🔊"),
+        ]),
+    ]
+}
+
+/// Shows the current playback on the control panel
+pub async fn embed(client: &AuthCodePkceSpotify) -> Result<CreateEmbed, Error> {
+    let playback = client.current_playback(None, None::<Vec<_>>).await?;
+    let embed = match playback.as_ref().and_then(|v| Some((v, v.item.clone()?))) {
+        Some((playback, item)) => current_playback(playback, item, CreateEmbed::new()).await,
+        None => current_no_playback(CreateEmbed::new()).await,
+    };
+
+    Ok(embed)
+}
+
+/// Runs a control panel button; Other interactions are ignored
+pub async fn handle(
+    ctx: &serenity::Context,
+    framework: poise::FrameworkContext<'_, Data, Error>,
+    data: &Data,
+    interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+    let action = match Action::parse(&interaction.data.custom_id) {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let actor = Actor::from_component(ctx, &framework.options.owners, interaction)?;
+
+    // Skipping with a vote needs /next, unless the user can skip without one
+    let settings = db_get_settings(&data.pool, actor.guild_id.get() as i64).await?;
+    let mut denial = playback_denial(data, &actor, action.capability())
+        .await?
+        .map(|v| v.to_string());
+    if denial.is_none()
+        && matches!(action, Action::Next)
+        && settings.vote_skip
+        && !access::has_capability(data, &actor, Capability::ForceSkip).await?
+    {
+        denial = Some("Skipping needs a vote; use /next to start one".to_string());
+    }
+    if let Some(reason) = denial {
+        let response = CreateInteractionResponseMessage::new()
+            .content(reason)
+            .ephemeral(true);
+        interaction
+            .create_response(ctx, CreateInteractionResponse::Message(response))
+            .await?;
+        return Ok(());
+    }
+
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await?;
+
+    let client = match data.client(actor.guild_id).await {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    if let Some(event) = run_action(data, &actor, &client, action).await? {
+        audit::record(&data.pool, actor.guild_id, actor.user_id, &event).await?;
+        log_channel::post(&ctx.http, &data.pool, actor.guild_id, actor.user_id, &event).await?;
+    }
+    info!(
+        "{} used {} on the control panel",
+        actor.user_id,
+        action.name()
+    );
+
+    let embed = embed(&client).await?;
+    interaction
+        .edit_response(
+            ctx,
+            EditInteractionResponse::new()
+                .embed(embed)
+                .components(components()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Returns what to write to the audit log; None if nothing was playing
+async fn run_action(
+    data: &Data,
+    actor: &Actor,
+    client: &AuthCodePkceSpotify,
+    action: Action,
+) -> Result<Option<Event>, Error> {
+    let playback = match client.current_playback(None, None::<Vec<_>>).await? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let event = match action {
+        Action::Previous | Action::Next => {
+            let (kind, skipped) = match action {
+                Action::Previous => (
+                    AuditAction::Previous,
+                    service::previous_track(data, actor.guild_id, client).await?,
+                ),
+                _ => (
+                    AuditAction::Skip,
+                    service::next_track(data, actor.guild_id, client).await?,
+                ),
+            };
+            match skipped {
+                Some(item) => Event::new(kind).target(item.get_title()),
+                None => Event::new(kind),
+            }
+        }
+        Action::Toggle if playback.is_playing => {
+            client.pause_playback(None).await?;
+            Event::new(AuditAction::Pause).to("Paused")
+        }
+        Action::Toggle => {
+            client.resume_playback(None, None).await?;
+            Event::new(AuditAction::Pause).to("Playing")
+        }
+        Action::Shuffle => {
+            client.shuffle(!playback.shuffle_state, None).await?;
+            Event::new(AuditAction::Shuffle).to(if playback.shuffle_state { "Off" } else { "On" })
+        }
+        Action::Repeat => {
+            let (next, name) = match playback.repeat_state {
+                RepeatState::Off => (RepeatState::Context, "Context"),
+                RepeatState::Context => (RepeatState::Track, "Track"),
+                RepeatState::Track => (RepeatState::Off, "Off"),
+            };
+            client.repeat(next, None).await?;
+            Event::new(AuditAction::Repeat).to(name)
+        }
+        Action::VolumeDown | Action::VolumeUp => {
+            let old = playback.device.volume_percent.unwrap_or_default();
+            let volume = match action {
+                Action::VolumeUp => (old + VOLUME_STEP).min(100),
+                _ => old.saturating_sub(VOLUME_STEP),
+            };
+            client.volume(volume as u8, None).await?;
+            Event::new(AuditAction::Volume)
+                .from(format!("{old}%"))
+                .to(format!("{volume}%"))
+        }
+    };
+
+    Ok(Some(event))
+}
@@ -0,0 +1,102 @@
+use std::{collections::HashSet, fmt, str::FromStr};
+
+/// Actions a role can grant; Stored by name in `role_capabilities`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    Queue,
+    Skip,
+    ForceSkip,
+    Previous,
+    Pause,
+    Seek,
+    Shuffle,
+    Repeat,
+    Volume,
+    Device,
+    ManageQueue,
+    ManageBlocklist,
+    Freeze,
+    ManageUsers,
+    ManageSettings,
+    ViewAudit,
+}
+
+impl Capability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Queue => "queue",
+            Capability::Skip => "skip",
+            Capability::ForceSkip => "force_skip",
+            Capability::Previous => "previous",
+            Capability::Pause => "pause",
+            Capability::Seek => "seek",
+            Capability::Shuffle => "shuffle",
+            Capability::Repeat => "repeat",
+            Capability::Volume => "volume",
+            Capability::Device => "device",
+            Capability::ManageQueue => "manage_queue",
+            Capability::ManageBlocklist => "manage_blocklist",
+            Capability::Freeze => "freeze",
+            Capability::ManageUsers => "manage_users",
+            Capability::ManageSettings => "manage_settings",
+            Capability::ViewAudit => "view_audit",
+        }
+    }
+}
+
+impl FromStr for Capability {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "queue" => Ok(Capability::Queue),
+            "skip" => Ok(Capability::Skip),
+            "force_skip" => Ok(Capability::ForceSkip),
+            "previous" => Ok(Capability::Previous),
+            "pause" => Ok(Capability::Pause),
+            "seek" => Ok(Capability::Seek),
+            "shuffle" => Ok(Capability::Shuffle),
+            "repeat" => Ok(Capability::Repeat),
+            "volume" => Ok(Capability::Volume),
+            "device" => Ok(Capability::Device),
+            "manage_queue" => Ok(Capability::ManageQueue),
+            "manage_blocklist" => Ok(Capability::ManageBlocklist),
+            "freeze" => Ok(Capability::Freeze),
+            "manage_users" => Ok(Capability::ManageUsers),
+            "manage_settings" => Ok(Capability::ManageSettings),
+            "view_audit" => Ok(Capability::ViewAudit),
+            _ => Err(format!("Unknown capability '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A named group of capabilities assigned to users
+#[derive(Debug, Clone)]
+pub struct Role {
+    pub name: String,
+    pub level: i16,
+    pub capabilities: HashSet<Capability>,
+}
+
+impl Role {
+    /// Builds a role from database values; Unknown capabilities are ignored
+    pub fn new(name: String, level: i16, capabilities: Vec<String>) -> Self {
+        let capabilities = capabilities.iter().filter_map(|v| v.parse().ok()).collect();
+
+        Self {
+            name,
+            level,
+            capabilities,
+        }
+    }
+
+    pub fn can(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
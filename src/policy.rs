@@ -0,0 +1,46 @@
+use chrono::TimeDelta;
+use poise::serenity_prelude::GuildId;
+
+use crate::database::{db_get_settings, GuildSettings};
+use crate::spotify::{ItemId, StandardItem};
+use crate::{format_delta, Error};
+
+/// What a guild allows to be queued, beyond its blocklist
+#[derive(Debug, Clone)]
+pub struct QueuePolicy {
+    pub allow_explicit: bool,
+    pub max_duration: Option<TimeDelta>,
+    pub allow_podcasts: bool,
+}
+
+impl QueuePolicy {
+    pub async fn load(pool: &sqlx::PgPool, guild_id: GuildId) -> Result<Self, Error> {
+        let settings = db_get_settings(pool, guild_id.get() as i64).await?;
+        Ok(Self::from(&settings))
+    }
+
+    /// Returns why the item can't be queued, or none if it can
+    pub fn reason(&self, item: &StandardItem<'_>) -> Option<String> {
+        if item.explicit && !self.allow_explicit {
+            return Some("Explicit content isn't allowed".to_string());
+        }
+        if matches!(item.id, ItemId::Episode(_)) && !self.allow_podcasts {
+            return Some("Podcasts aren't allowed".to_string());
+        }
+        if let Some(max) = self.max_duration.filter(|v| item.duration > *v) {
+            return Some(format!("Longer than the {} limit", format_delta(max)));
+        }
+
+        None
+    }
+}
+
+impl From<&GuildSettings> for QueuePolicy {
+    fn from(settings: &GuildSettings) -> Self {
+        Self {
+            allow_explicit: settings.allow_explicit,
+            max_duration: settings.max_duration.map(|v| TimeDelta::seconds(v as i64)),
+            allow_podcasts: settings.allow_podcasts,
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::TimeDelta;
+use poise::serenity_prelude::GuildId;
+use rspotify::{prelude::OAuthClient, AuthCodePkceSpotify};
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+use crate::blocklist::Blocklist;
+use crate::database::{db_play_released, db_play_started, db_remove_play};
+use crate::policy::QueuePolicy;
+use crate::requests::RequestQueue;
+use crate::spotify::StandardItem;
+use crate::Error;
+
+/// How often every guild's playback is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How long before the current song ends the next request is sent to Spotify;
+/// Until then it can still be removed or moved
+const RELEASE_BEFORE_END: TimeDelta = TimeDelta::seconds(20);
+
+/// What the poller remembers about a guild between polls
+#[derive(Default)]
+pub struct GuildState {
+    /// The last song seen playing
+    last_seen: Option<String>,
+    /// A request sent to Spotify's queue that hasn't started yet
+    awaiting: Option<String>,
+}
+
+/// Watches the playback of every guild in the background, recording what was played
+/// and sending requests to Spotify's queue one at a time, as each song nears its end
+pub async fn run(
+    pool: sqlx::PgPool,
+    spotify: Arc<RwLock<HashMap<GuildId, AuthCodePkceSpotify>>>,
+    requests: Arc<RwLock<HashMap<GuildId, RequestQueue>>>,
+) {
+    let mut states: HashMap<GuildId, GuildState> = HashMap::new();
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        // Clone the clients so requests don't hold the lock
+        let clients: Vec<_> = spotify
+            .read()
+            .await
+            .iter()
+            .map(|(guild_id, client)| (*guild_id, client.clone()))
+            .collect();
+
+        for (guild_id, client) in clients {
+            let state = states.entry(guild_id).or_default();
+            if let Err(err) = poll_guild(&pool, guild_id, &client, &requests, state).await {
+                debug!("Failed to poll playback for {guild_id}: {err}");
+            }
+        }
+    }
+}
+
+/// Records the guild's song if it changed, and releases the next request just before it ends
+pub async fn poll_guild(
+    pool: &sqlx::PgPool,
+    guild_id: GuildId,
+    client: &AuthCodePkceSpotify,
+    requests: &RwLock<HashMap<GuildId, RequestQueue>>,
+    state: &mut GuildState,
+) -> Result<(), Error> {
+    let playing = match client.current_playing(None, None::<Vec<_>>).await? {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let item = match playing.item {
+        Some(item) => StandardItem::parse(item),
+        None => return Ok(()),
+    };
+
+    let uri = item.uri();
+    if state.last_seen.as_ref() != Some(&uri) {
+        db_play_started(pool, guild_id.get() as i64, &item).await?;
+        state.last_seen = Some(uri.clone());
+    }
+
+    // Only one request waits in Spotify's queue at a time
+    if let Some(awaiting) = &state.awaiting {
+        if awaiting != &uri {
+            let queued = client
+                .current_user_queue()
+                .await?
+                .queue
+                .into_iter()
+                .any(|v| &StandardItem::parse(v).uri() == awaiting);
+            if queued {
+                return Ok(());
+            }
+        }
+        // Either it started, or it was removed from Spotify's queue
+        state.awaiting = None;
+    }
+
+    let remaining = item.duration - playing.progress.unwrap_or_default();
+    if !playing.is_playing || remaining > RELEASE_BEFORE_END {
+        return Ok(());
+    }
+
+    let request = match requests.write().await.get_mut(&guild_id) {
+        Some(queue) => queue.pop(),
+        None => None,
+    };
+    let request = match request {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+
+    // It may have been blocked, or the policy changed, after it was requested
+    let policy = QueuePolicy::load(pool, guild_id).await?;
+    let blocklist = Blocklist::load(pool, guild_id).await?;
+    let reason = policy
+        .reason(&request.item)
+        .or_else(|| blocklist.reason(&request.item));
+    if let Some(reason) = reason {
+        db_remove_play(
+            pool,
+            guild_id.get() as i64,
+            &request.item,
+            request.requester.get() as i64,
+        )
+        .await?;
+        info!(
+            "Dropped {} requested by {} from {guild_id}: {reason}",
+            request.item.get_title(),
+            request.requester
+        );
+        return Ok(());
+    }
+
+    if let Err(err) = client
+        .add_item_to_queue(request.item.playable_id(), None)
+        .await
+    {
+        // Try again on the next poll
+        requests
+            .write()
+            .await
+            .entry(guild_id)
+            .or_default()
+            .push_front(request);
+        return Err(err.into());
+    }
+
+    db_play_released(
+        pool,
+        guild_id.get() as i64,
+        &request.item,
+        request.requester.get() as i64,
+    )
+    .await?;
+    info!(
+        "Sent {} requested by {} to the queue of {guild_id}",
+        request.item.get_title(),
+        request.requester
+    );
+    state.awaiting = Some(request.item.uri());
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use poise::serenity_prelude::UserId;
+
+use crate::spotify::StandardItem;
+
+/// A song someone asked the bot to queue
+#[derive(Clone)]
+pub struct Request {
+    pub item: StandardItem<'static>,
+    pub requester: UserId,
+}
+
+/// Songs waiting to be sent to Spotify, ordered so every requester takes turns
+#[derive(Default)]
+pub struct RequestQueue {
+    requests: Vec<Request>,
+}
+
+impl RequestQueue {
+    /// Adds the request behind everyone's request of the same round; Returns its index.
+    /// A requester's first song is round 0, their second round 1, and so on
+    pub fn push(&mut self, request: Request) -> usize {
+        let round = self
+            .requests
+            .iter()
+            .filter(|v| v.requester == request.requester)
+            .count();
+
+        // Insert before the first request of a later round
+        let mut rounds: HashMap<UserId, usize> = HashMap::new();
+        let index = self
+            .requests
+            .iter()
+            .position(|v| {
+                let count = rounds.entry(v.requester).or_default();
+                *count += 1;
+                *count - 1 > round
+            })
+            .unwrap_or(self.requests.len());
+
+        self.requests.insert(index, request);
+        index
+    }
+
+    /// Puts a request back at the front, such as when sending it to Spotify failed
+    pub fn push_front(&mut self, request: Request) {
+        self.requests.insert(0, request);
+    }
+
+    /// Takes the next request to send to Spotify
+    pub fn pop(&mut self) -> Option<Request> {
+        if self.requests.is_empty() {
+            return None;
+        }
+
+        Some(self.requests.remove(0))
+    }
+
+    /// Takes the request at the index out of the queue
+    pub fn remove(&mut self, index: usize) -> Option<Request> {
+        if index >= self.requests.len() {
+            return None;
+        }
+
+        Some(self.requests.remove(index))
+    }
+
+    /// Moves a request to another index, shifting the ones between; Returns false if either is out of range
+    pub fn move_to(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.requests.len() || to >= self.requests.len() {
+            return false;
+        }
+
+        let request = self.requests.remove(from);
+        self.requests.insert(to, request);
+        true
+    }
+
+    /// Takes every request matching the filter out of the queue
+    pub fn remove_where(&mut self, filter: impl Fn(&Request) -> bool) -> Vec<Request> {
+        let (removed, kept) = self.requests.drain(..).partition(|v| filter(v));
+        self.requests = kept;
+        removed
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Request> {
+        self.requests.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Request> {
+        self.requests.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+}
@@ -0,0 +1,311 @@
+use chrono::TimeDelta;
+use poise::serenity_prelude::{GuildId, RoleId, UserId};
+use rspotify::{prelude::OAuthClient, AuthCodePkceSpotify};
+
+use crate::access::{self, change_denial, playback_denial, Actor, Reason};
+use crate::blocklist::Blocklist;
+use crate::database::{
+    db_add_play, db_count_requests, db_get_quota, db_get_requesters, db_get_settings, Quota,
+};
+use crate::permissions::Capability;
+use crate::policy::QueuePolicy;
+use crate::requests::Request;
+use crate::spotify::{fetch_link, fetch_queue, record_skip, SpotifyLink, StandardItem};
+use crate::{Data, Error};
+
+/// A user's quota and how much of it is used
+pub struct QuotaUsage {
+    pub quota: Quota,
+    pub used: i64,
+    /// When the oldest request stops counting
+    pub resets: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Where the first song of a request ended up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Started on the preferred device, since nothing was playing
+    PlayingNow,
+    /// Waiting in the bot's queue; Starts at 1
+    Waiting(usize),
+}
+
+/// Songs that were queued
+pub struct Queued {
+    /// Name of the album or playlist; None for a single song
+    pub name: Option<String>,
+    pub items: Vec<StandardItem<'static>>,
+    pub position: Position,
+    /// Songs left out of the album or playlist, with why
+    pub skipped: Vec<(StandardItem<'static>, String)>,
+}
+
+/// What happened when queuing a link
+pub enum QueueOutcome {
+    Added(Queued),
+    Denied(Reason),
+    /// The link has nothing that can be queued
+    Empty,
+    /// Everything in the link is blocked or against the server's policy
+    Blocked {
+        item: StandardItem<'static>,
+        reason: String,
+    },
+}
+
+/// The current song and everything after it
+pub struct QueueView {
+    pub current: StandardItem<'static>,
+    pub progress: TimeDelta,
+    /// Spotify's queue, with who requested each song through the bot
+    pub upcoming: Vec<(StandardItem<'static>, Option<UserId>)>,
+    /// Requests the bot hasn't sent to Spotify yet
+    pub waiting: Vec<Request>,
+    /// Until everything finishes playing
+    pub remaining: TimeDelta,
+}
+
+/// Returns how much of their quota the user used; Returns none if they're unlimited
+pub async fn quota_usage(
+    data: &Data,
+    guild_id: GuildId,
+    user_id: UserId,
+    roles: &[RoleId],
+) -> Result<Option<QuotaUsage>, Error> {
+    let guild = guild_id.get() as i64;
+    let id = user_id.get() as i64;
+    let role = access::user_role(data, guild_id, user_id, roles).await?;
+
+    let quota = match db_get_quota(
+        &data.pool,
+        guild,
+        id,
+        role.as_ref().map(|v| v.name.as_str()),
+    )
+    .await?
+    {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let (used, oldest) = db_count_requests(&data.pool, guild, id, quota.period).await?;
+    let resets = oldest.map(|v| v + TimeDelta::seconds(quota.period as i64));
+    Ok(Some(QuotaUsage {
+        quota,
+        used,
+        resets,
+    }))
+}
+
+/// Returns a denial if the actor can't request that many more songs; Owners are unlimited
+pub async fn quota_denial(
+    data: &Data,
+    actor: &Actor,
+    requests: i64,
+) -> Result<Option<Reason>, Error> {
+    if requests == 0 || access::is_owner(data, actor).await? {
+        return Ok(None);
+    }
+
+    let usage = match quota_usage(data, actor.guild_id, actor.user_id, &actor.roles).await? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    if usage.used + requests <= usage.quota.max_requests as i64 {
+        return Ok(None);
+    }
+
+    Ok(Some(Reason::Quota {
+        used: usage.used,
+        max_requests: usage.quota.max_requests,
+        period: usage.quota.period,
+        resets: usage.resets,
+    }))
+}
+
+/// Returns the id of the server's preferred device, if nothing is playing and it's available
+pub async fn idle_device(data: &Data, guild_id: GuildId) -> Result<Option<String>, Error> {
+    let client = match data.client(guild_id).await {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    if client
+        .current_playing(None, None::<Vec<_>>)
+        .await?
+        .is_some()
+    {
+        return Ok(None);
+    }
+
+    let preferred = match db_get_settings(&data.pool, guild_id.get() as i64)
+        .await?
+        .preferred_device
+    {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let device = client
+        .device()
+        .await?
+        .into_iter()
+        .find(|v| v.name.eq_ignore_ascii_case(&preferred));
+    Ok(device.and_then(|v| v.id))
+}
+
+/// Returns why the actor can't queue a song right now, or none if they can.
+/// When nothing is playing, songs can start on the preferred device
+async fn device_denial(data: &Data, actor: &Actor, idle: bool) -> Result<Option<Reason>, Error> {
+    let denial = match idle {
+        true => change_denial(data, actor, Capability::Queue).await?,
+        false => playback_denial(data, actor, Capability::Queue).await?,
+    };
+    if denial.is_some() {
+        return Ok(denial);
+    }
+
+    quota_denial(data, actor, 1).await
+}
+
+/// Queues everything the link points to, up to the server's limit
+pub async fn queue_link(
+    data: &Data,
+    actor: &Actor,
+    link: &SpotifyLink,
+) -> Result<QueueOutcome, Error> {
+    let device = idle_device(data, actor.guild_id).await?;
+    if let Some(reason) = device_denial(data, actor, device.is_some()).await? {
+        return Ok(QueueOutcome::Denied(reason));
+    }
+    let client = match data.client(actor.guild_id).await {
+        Some(v) => v,
+        None => return Ok(QueueOutcome::Denied(Reason::Unauthorized)),
+    };
+
+    let guild = actor.guild_id.get() as i64;
+    let settings = db_get_settings(&data.pool, guild).await?;
+    let found = fetch_link(&client, link, settings.collection_limit as usize).await?;
+    if found.items.is_empty() {
+        return Ok(QueueOutcome::Empty);
+    }
+
+    // Songs that are blocked or against the server's policy are left out of albums and playlists
+    let policy = QueuePolicy::from(&settings);
+    let blocklist = Blocklist::load(&data.pool, actor.guild_id).await?;
+    let mut items = Vec::new();
+    let mut skipped = Vec::new();
+    for item in found.items {
+        match policy.reason(&item).or_else(|| blocklist.reason(&item)) {
+            Some(reason) => skipped.push((item, reason)),
+            None => items.push(item),
+        }
+    }
+    if items.is_empty() {
+        let (item, reason) = skipped.remove(0);
+        return Ok(QueueOutcome::Blocked { item, reason });
+    }
+
+    if let Some(reason) = quota_denial(data, actor, items.len() as i64).await? {
+        return Ok(QueueOutcome::Denied(reason));
+    }
+
+    let requester = actor.user_id.get() as i64;
+    let mut position = None;
+    let mut rest = items.iter();
+    if let Some(device) = &device {
+        // Only recorded once it plays, so a failed start doesn't count against the quota
+        let first = rest.next().unwrap();
+        client
+            .start_uris_playback([first.playable_id()], Some(device), None, None)
+            .await?;
+        db_add_play(&data.pool, guild, first, requester, false).await?;
+        position = Some(Position::PlayingNow);
+    }
+
+    // Requests are sent to Spotify one at a time, taking turns between requesters
+    for item in rest {
+        db_add_play(&data.pool, guild, item, requester, true).await?;
+        let index = data
+            .requests
+            .write()
+            .await
+            .entry(actor.guild_id)
+            .or_default()
+            .push(Request {
+                item: item.clone(),
+                requester: actor.user_id,
+            });
+        position.get_or_insert(Position::Waiting(index + 1));
+    }
+
+    Ok(QueueOutcome::Added(Queued {
+        name: found.name,
+        items,
+        position: position.unwrap(),
+        skipped,
+    }))
+}
+
+/// Returns the current song and what plays after it; None if nothing is playing
+pub async fn queue_view(
+    data: &Data,
+    guild_id: GuildId,
+    client: &AuthCodePkceSpotify,
+) -> Result<Option<QueueView>, Error> {
+    let (current, progress) = match client.current_playing(None, None::<Vec<_>>).await? {
+        Some(v) => match v.item {
+            Some(item) => (StandardItem::parse(item), v.progress.unwrap_or_default()),
+            None => return Ok(None),
+        },
+        None => return Ok(None),
+    };
+
+    let queue = fetch_queue(client).await?;
+    let uris = queue.iter().map(|v| v.uri()).collect::<Vec<_>>();
+    let requesters = db_get_requesters(&data.pool, guild_id.get() as i64, &uris).await?;
+    let upcoming = queue
+        .into_iter()
+        .map(|v| {
+            let requester = requesters.get(&v.uri()).map(|v| UserId::new(*v as u64));
+            (v, requester)
+        })
+        .collect::<Vec<_>>();
+
+    let waiting = match data.requests.read().await.get(&guild_id) {
+        Some(requests) => requests.iter().cloned().collect(),
+        None => Vec::new(),
+    };
+
+    let remaining = current.duration - progress
+        + upcoming.iter().map(|v| v.0.duration).sum::<TimeDelta>()
+        + waiting.iter().map(|v| v.item.duration).sum::<TimeDelta>();
+    Ok(Some(QueueView {
+        current,
+        progress,
+        upcoming,
+        waiting,
+        remaining,
+    }))
+}
+
+/// Skips to the next song, marking the current one as skipped; Returns the skipped song
+pub async fn next_track(
+    data: &Data,
+    guild_id: GuildId,
+    client: &AuthCodePkceSpotify,
+) -> Result<Option<StandardItem<'static>>, Error> {
+    let skipped = record_skip(&data.pool, guild_id, client).await?;
+    client.next_track(None).await?;
+    Ok(skipped)
+}
+
+/// Goes back to the previous song, marking the current one as skipped; Returns the skipped song
+pub async fn previous_track(
+    data: &Data,
+    guild_id: GuildId,
+    client: &AuthCodePkceSpotify,
+) -> Result<Option<StandardItem<'static>>, Error> {
+    let skipped = record_skip(&data.pool, guild_id, client).await?;
+    client.previous_track(None).await?;
+    Ok(skipped)
+}
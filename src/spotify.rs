@@ -1,18 +1,37 @@
+use std::{
+    collections::HashMap,
+    env,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use chrono::TimeDelta;
+use poise::serenity_prelude::{GuildId, UserId};
 use rspotify::{
-    model::{EpisodeId, FullEpisode, FullTrack, PlayableItem, TrackId},
-    prelude::{BaseClient, OAuthClient},
-    scopes, AuthCodePkceSpotify, OAuth,
+    model::{
+        AlbumId, EpisodeId, FullEpisode, FullTrack, PlayableId, PlayableItem, PlaylistId,
+        SearchResult, SearchType, ShowId, TrackId,
+    },
+    prelude::{BaseClient, Id, OAuthClient},
+    scopes, AuthCodePkceSpotify, OAuth, Token, TokenCallback,
 };
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{error, warn};
 
-use crate::{Context, Error};
+use crate::blocklist::Blocklist;
+use crate::crypto::TokenCipher;
+use crate::database::{db_get_tokens, db_play_skipped, db_save_token};
+use crate::policy::QueuePolicy;
+use crate::{Context, Data, Error};
 
+#[derive(Clone)]
 pub enum ItemId<'a> {
     Track(TrackId<'a>),
     Episode(EpisodeId<'a>),
 }
 
+#[derive(Clone)]
 pub struct StandardItem<'a> {
     pub name: String,
     pub duration: TimeDelta,
@@ -20,6 +39,42 @@ pub struct StandardItem<'a> {
     pub image: String,
     pub url: String,
     pub id: ItemId<'a>,
+    /// Spotify ids of the artists; Empty for episodes
+    pub artist_ids: Vec<String>,
+    /// Spotify id of the album; None for episodes
+    pub album_id: Option<String>,
+    pub explicit: bool,
+    /// From 0 to 100; None for episodes
+    pub popularity: Option<u32>,
+}
+
+/// Most tracks Spotify returns in one request
+const TRACKS_PER_REQUEST: usize = 50;
+
+/// How long search suggestions are reused
+const SEARCH_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Search suggestions shown while typing in /play
+pub struct CachedSearch {
+    pub fetched: Instant,
+    /// Every song found, including those the server doesn't allow
+    pub results: Vec<StandardItem<'static>>,
+}
+
+/// What a Spotify URL or URI points to
+pub enum SpotifyLink {
+    Track(TrackId<'static>),
+    Episode(EpisodeId<'static>),
+    Album(AlbumId<'static>),
+    Playlist(PlaylistId<'static>),
+    /// Queues the show's latest episode
+    Show(ShowId<'static>),
+}
+
+/// Songs found from a link; Name is set for albums and playlists
+pub struct LinkItems<'a> {
+    pub name: Option<String>,
+    pub items: Vec<StandardItem<'a>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -52,6 +107,22 @@ impl StandardItem<'_> {
         str
     }
 
+    /// Spotify URI of the track or episode
+    pub fn uri(&self) -> String {
+        match &self.id {
+            ItemId::Track(v) => v.uri(),
+            ItemId::Episode(v) => v.uri(),
+        }
+    }
+
+    /// Id used to add the item to the queue
+    pub fn playable_id(&self) -> PlayableId<'_> {
+        match &self.id {
+            ItemId::Track(v) => PlayableId::Track(v.as_ref()),
+            ItemId::Episode(v) => PlayableId::Episode(v.as_ref()),
+        }
+    }
+
     pub fn get_track_id(&self) -> Option<TrackId<'_>> {
         if let ItemId::Track(v) = &self.id {
             Some(v.clone())
@@ -61,7 +132,61 @@ impl StandardItem<'_> {
     }
 }
 
-pub async fn init() -> Result<rspotify::AuthCodePkceSpotify, Error> {
+impl SpotifyLink {
+    /// Parses `open.spotify.com` URLs (including `/intl-xx/` ones) and `spotify:` URIs
+    pub fn parse(input: &str) -> Option<Self> {
+        let (kind, id) = split_link(input)?;
+
+        let id = id.to_string();
+        match kind {
+            "track" => TrackId::from_id(id).ok().map(SpotifyLink::Track),
+            "episode" => EpisodeId::from_id(id).ok().map(SpotifyLink::Episode),
+            "album" => AlbumId::from_id(id).ok().map(SpotifyLink::Album),
+            "playlist" => PlaylistId::from_id(id).ok().map(SpotifyLink::Playlist),
+            "show" => ShowId::from_id(id).ok().map(SpotifyLink::Show),
+            _ => None,
+        }
+    }
+
+    /// Name of what the link points to, for replies
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SpotifyLink::Track(_) => "Song",
+            SpotifyLink::Episode(_) | SpotifyLink::Show(_) => "Episode",
+            SpotifyLink::Album(_) => "Album",
+            SpotifyLink::Playlist(_) => "Playlist",
+        }
+    }
+}
+
+/// Splits a Spotify URL or URI into the kind of item (track, album, ...) and its id
+pub fn split_link(input: &str) -> Option<(&str, &str)> {
+    let input = input.trim();
+    if let Some(uri) = input.strip_prefix("spotify:") {
+        return uri.split_once(':');
+    }
+
+    let path = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+        .unwrap_or(input)
+        .strip_prefix("open.spotify.com/")?;
+    let path = path.split(['?', '#']).next()?;
+
+    let mut segments = path.split('/').filter(|v| !v.is_empty());
+    let mut kind = segments.next()?;
+    if kind.starts_with("intl-") {
+        kind = segments.next()?;
+    }
+    Some((kind, segments.next()?))
+}
+
+pub async fn init(
+    pool: &sqlx::PgPool,
+    cipher: &TokenCipher,
+    guild_id: GuildId,
+    owner_id: Option<UserId>,
+) -> Result<rspotify::AuthCodePkceSpotify, Error> {
     let creds = rspotify::Credentials::from_env().expect("Credentials Not Provided");
 
     // Using every possible scope
@@ -72,8 +197,33 @@ pub async fn init() -> Result<rspotify::AuthCodePkceSpotify, Error> {
         "user-read-recently-played"
     );
     let oauth = OAuth::from_env(scopes).unwrap();
+
+    // Persist every new or refreshed token so it survives restarts; One task saves them in order,
+    // so an older token can't overwrite a newer one. It stops when the client is dropped
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Token>();
+    let (pool, cipher) = (pool.clone(), cipher.clone());
+    tokio::spawn(async move {
+        while let Some(token) = receiver.recv().await {
+            if let Err(err) = save_token(&pool, &cipher, guild_id, owner_id, &token).await {
+                error!("Failed to save Spotify token for {guild_id}: {err}");
+            }
+        }
+    });
+    let callback = TokenCallback(Box::new(move |token: Token| {
+        if sender.send(token).is_err() {
+            error!("Failed to save Spotify token for {guild_id}: the writer stopped");
+        }
+        Ok(())
+    }));
+
+    // Can point at another server, like a fake one in tests
+    let api_base_url =
+        env::var("SPOTIFY_API_URL").unwrap_or_else(|_| rspotify::DEFAULT_API_BASE_URL.to_string());
+
     let config = rspotify::Config {
+        api_base_url,
         token_refreshing: true,
+        token_callback_fn: Arc::new(Some(callback)),
         ..Default::default()
     };
 
@@ -84,19 +234,81 @@ pub async fn init() -> Result<rspotify::AuthCodePkceSpotify, Error> {
     ))
 }
 
-pub async fn fetch_queue(ctx: Context<'_>) -> Result<Vec<StandardItem<'_>>, Error> {
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
-        Some(v) => v,
-        None => {
-            return Err("Unauthorized".into());
+/// Loads the stored tokens from the database, skipping any that aren't usable
+pub async fn restore(
+    pool: &sqlx::PgPool,
+    cipher: &TokenCipher,
+) -> Result<HashMap<GuildId, AuthCodePkceSpotify>, Error> {
+    let mut clients = HashMap::new();
+
+    for (guild_id, owner_id, data) in db_get_tokens(pool).await? {
+        // Tokens from before multi-guild support belong to guild 0 until LEGACY_GUILD_ID adopts them
+        if guild_id == 0 {
+            warn!("A Spotify token from before multi-guild support isn't used; set LEGACY_GUILD_ID to the server it belongs to");
+            continue;
         }
+        let guild_id = GuildId::new(guild_id as u64);
+        let token: Token = match cipher
+            .decrypt(&data)
+            .and_then(|v| serde_json::from_slice(&v).map_err(Into::into))
+        {
+            Ok(v) => v,
+            Err(err) => {
+                warn!("Stored Spotify token for {guild_id} is unusable, re-authentication required: {err}");
+                continue;
+            }
+        };
+
+        // Tokens from before multi-guild support don't know who authenticated
+        let owner_id = owner_id.map(|v| UserId::new(v as u64));
+        let client = init(pool, cipher, guild_id, owner_id).await?;
+        *client.get_token().lock().await.unwrap() = Some(token);
+        clients.insert(guild_id, client);
+    }
+
+    Ok(clients)
+}
+
+/// Encrypts and writes the token to the database
+pub async fn save_token(
+    pool: &sqlx::PgPool,
+    cipher: &TokenCipher,
+    guild_id: GuildId,
+    owner_id: Option<UserId>,
+    token: &Token,
+) -> Result<(), Error> {
+    let data = cipher.encrypt(&serde_json::to_vec(token)?)?;
+    let owner_id = owner_id.map(|v| v.get() as i64);
+    db_save_token(pool, guild_id.get() as i64, owner_id, &data).await
+}
+
+/// Returns the Spotify client of the guild the command was run in
+pub async fn client(ctx: Context<'_>) -> Option<AuthCodePkceSpotify> {
+    let guild_id = ctx.guild_id()?;
+    ctx.data().client(guild_id).await
+}
+
+/// Marks the current song as skipped in the history; Returns the song, if one is playing
+pub async fn record_skip(
+    pool: &sqlx::PgPool,
+    guild_id: GuildId,
+    client: &AuthCodePkceSpotify,
+) -> Result<Option<StandardItem<'static>>, Error> {
+    let item = match client.current_playing(None, None::<Vec<_>>).await? {
+        Some(v) => match v.item {
+            Some(item) => StandardItem::parse(item),
+            None => return Ok(None),
+        },
+        None => return Ok(None),
     };
 
+    db_play_skipped(pool, guild_id.get() as i64, &item).await?;
+    Ok(Some(item))
+}
+
+/// Returns Spotify's queue, after the current song
+pub async fn fetch_queue<'a>(client: &AuthCodePkceSpotify) -> Result<Vec<StandardItem<'a>>, Error> {
     let data = client.current_user_queue().await?.queue;
-    // Free client lock
-    drop(lock);
 
     let mut queue = Vec::new();
     for item in data {
@@ -107,25 +319,172 @@ pub async fn fetch_queue(ctx: Context<'_>) -> Result<Vec<StandardItem<'_>>, Erro
     Ok(queue)
 }
 
-pub async fn fetch_track<'a>(
-    ctx: Context<'_>,
-    track: TrackId<'_>,
-) -> Result<StandardItem<'a>, Error> {
-    // Lock Client to get response
-    let lock = ctx.data().spotify.read().await;
-    let client = match &*lock {
+/// Searches songs for suggestions, returning their titles and URIs; Results are cached.
+/// Blocked songs and those against the server's policy are left out
+pub async fn search_tracks(
+    data: &Data,
+    guild_id: GuildId,
+    query: &str,
+) -> Result<Vec<(String, String)>, Error> {
+    let policy = QueuePolicy::load(&data.pool, guild_id).await?;
+    let blocklist = Blocklist::load(&data.pool, guild_id).await?;
+    let allowed = |items: &[StandardItem<'static>]| {
+        items
+            .iter()
+            .filter(|v| policy.reason(v).is_none() && blocklist.reason(v).is_none())
+            .map(|v| (v.get_title(), v.uri()))
+            .collect::<Vec<_>>()
+    };
+
+    // Cached unfiltered, so blocking a song hides it straight away
+    let key = (guild_id, query.to_lowercase());
+    if let Some(cached) = data.search_cache.read().await.get(&key) {
+        if cached.fetched.elapsed() < SEARCH_CACHE_TTL {
+            return Ok(allowed(&cached.results));
+        }
+    }
+
+    let client = match data.client(guild_id).await {
         Some(v) => v,
         None => {
             return Err("Unauthorized".into());
         }
     };
 
-    let data = client.track(track, None).await?;
-    // Free client lock
-    drop(lock);
+    let results = match client
+        .search(query, SearchType::Track, None, None, Some(10), None)
+        .await?
+    {
+        SearchResult::Tracks(page) => page
+            .items
+            .into_iter()
+            .filter(|v| v.id.is_some())
+            .map(|v| StandardItem::parse(PlayableItem::Track(v)))
+            .collect::<Vec<_>>(),
+        _ => return Err("Unexpected search result".into()),
+    };
+    let allowed = allowed(&results);
+
+    let mut cache = data.search_cache.write().await;
+    cache.retain(|_, v| v.fetched.elapsed() < SEARCH_CACHE_TTL);
+    cache.insert(
+        key,
+        CachedSearch {
+            fetched: Instant::now(),
+            results,
+        },
+    );
+
+    Ok(allowed)
+}
+
+/// Fetches the songs a link points to; Albums and playlists are cut off at the limit
+pub async fn fetch_link<'a>(
+    client: &AuthCodePkceSpotify,
+    link: &SpotifyLink,
+    limit: usize,
+) -> Result<LinkItems<'a>, Error> {
+    match link {
+        SpotifyLink::Track(id) => {
+            let data = client.track(id.clone(), None).await?;
+            Ok(LinkItems {
+                name: None,
+                items: vec![StandardItem::parse(PlayableItem::Track(data))],
+            })
+        }
+        SpotifyLink::Episode(id) => {
+            let data = client.get_an_episode(id.clone(), None).await?;
+            Ok(LinkItems {
+                name: None,
+                items: vec![StandardItem::parse(PlayableItem::Episode(data))],
+            })
+        }
+        SpotifyLink::Show(id) => {
+            let episodes = client
+                .get_shows_episodes_manual(id.clone(), None, Some(1), None)
+                .await?;
+            let mut items = Vec::new();
+            if let Some(latest) = episodes.items.into_iter().next() {
+                let data = client.get_an_episode(latest.id, None).await?;
+                items.push(StandardItem::parse(PlayableItem::Episode(data)));
+            }
+
+            Ok(LinkItems { name: None, items })
+        }
+        SpotifyLink::Album(id) => {
+            let album = client.album(id.clone(), None).await?;
+            let mut page = album.tracks;
+            let mut ids: Vec<TrackId<'static>> = Vec::new();
+            loop {
+                ids.extend(page.items.into_iter().filter_map(|v| v.id));
+                if ids.len() >= limit || page.next.is_none() {
+                    break;
+                }
+                page = client
+                    .album_track_manual(
+                        id.clone(),
+                        None,
+                        Some(TRACKS_PER_REQUEST as u32),
+                        Some(ids.len() as u32),
+                    )
+                    .await?;
+            }
+            ids.truncate(limit);
+
+            // Album tracks don't include the album, so fetch the full tracks for images
+            let mut items = Vec::new();
+            for chunk in ids.chunks(TRACKS_PER_REQUEST) {
+                let tracks = client.tracks(chunk.iter().cloned(), None).await?;
+                items.extend(
+                    tracks
+                        .into_iter()
+                        .map(|v| StandardItem::parse(PlayableItem::Track(v))),
+                );
+            }
+
+            Ok(LinkItems {
+                name: Some(album.name),
+                items,
+            })
+        }
+        SpotifyLink::Playlist(id) => {
+            let playlist = client.playlist(id.clone(), None, None).await?;
+            let mut page = playlist.tracks;
+            let mut offset = 0;
+            let mut items = Vec::new();
+            loop {
+                offset += page.items.len();
+                items.extend(
+                    page.items
+                        .into_iter()
+                        .filter(|v| !v.is_local)
+                        .filter_map(|v| v.track)
+                        .filter(is_playable)
+                        .map(StandardItem::parse),
+                );
+                if items.len() >= limit || page.next.is_none() {
+                    break;
+                }
+                page = client
+                    .playlist_items_manual(id.clone(), None, None, None, Some(offset as u32))
+                    .await?;
+            }
+            items.truncate(limit);
 
-    let data = StandardItem::parse(PlayableItem::Track(data));
-    Ok(data)
+            Ok(LinkItems {
+                name: Some(playlist.name),
+                items,
+            })
+        }
+    }
+}
+
+/// Local files can't be queued
+fn is_playable(item: &PlayableItem) -> bool {
+    match item {
+        PlayableItem::Track(track) => track.id.is_some(),
+        PlayableItem::Episode(_) => true,
+    }
 }
 
 pub fn handle_track_current<'a>(track: FullTrack) -> StandardItem<'a> {
@@ -140,6 +499,12 @@ pub fn handle_track_current<'a>(track: FullTrack) -> StandardItem<'a> {
         .collect::<Vec<String>>();
     let url = track.external_urls.get("spotify").unwrap().clone();
 
+    let artist_ids = track
+        .artists
+        .iter()
+        .filter_map(|artist| artist.id.as_ref().map(|v| v.id().to_string()))
+        .collect::<Vec<String>>();
+
     StandardItem {
         name: track.name.clone(),
         duration: track.duration,
@@ -147,6 +512,10 @@ pub fn handle_track_current<'a>(track: FullTrack) -> StandardItem<'a> {
         image,
         url,
         id: ItemId::Track(track.id.unwrap()),
+        artist_ids,
+        album_id: track.album.id.as_ref().map(|v| v.id().to_string()),
+        explicit: track.explicit,
+        popularity: Some(track.popularity),
     }
 }
 
@@ -164,5 +533,9 @@ pub fn handle_episode_current<'a>(track: FullEpisode) -> StandardItem<'a> {
         image,
         url,
         id: ItemId::Episode(track.id),
+        artist_ids: Vec::new(),
+        album_id: None,
+        explicit: track.explicit,
+        popularity: None,
     }
 }
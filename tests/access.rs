@@ -0,0 +1,150 @@
+mod common;
+
+use common::{actor, data, FakeSpotify, GUILD, LISTENER, OWNER, STRANGER};
+use delegatify::access::{
+    can_assign, can_manage, change_denial, has_capability, is_owner, playback_denial, Reason,
+};
+use delegatify::database::{db_add_user, db_get_roles, db_save_token};
+use delegatify::permissions::Capability;
+
+#[sqlx::test]
+async fn admins_can_do_everything(pool: sqlx::PgPool) {
+    let data = data(pool, None).await;
+    let admin = actor(STRANGER, true);
+
+    assert!(has_capability(&data, &admin, Capability::ManageSettings)
+        .await
+        .unwrap());
+    assert!(has_capability(&data, &admin, Capability::Device)
+        .await
+        .unwrap());
+}
+
+#[sqlx::test]
+async fn whoever_connected_spotify_is_an_owner(pool: sqlx::PgPool) {
+    db_save_token(
+        &pool,
+        GUILD.get() as i64,
+        Some(OWNER.get() as i64),
+        b"token",
+    )
+    .await
+    .unwrap();
+    let data = data(pool, None).await;
+
+    assert!(is_owner(&data, &actor(OWNER, false)).await.unwrap());
+    assert!(!is_owner(&data, &actor(STRANGER, false)).await.unwrap());
+}
+
+#[sqlx::test]
+async fn roles_grant_their_capabilities(pool: sqlx::PgPool) {
+    db_add_user(&pool, GUILD.get() as i64, LISTENER.get() as i64, "listener")
+        .await
+        .unwrap();
+    let data = data(pool, None).await;
+    let listener = actor(LISTENER, false);
+
+    assert!(has_capability(&data, &listener, Capability::Queue)
+        .await
+        .unwrap());
+    // Like level 1 before roles, listeners can skip and go back
+    assert!(has_capability(&data, &listener, Capability::Skip)
+        .await
+        .unwrap());
+    assert!(has_capability(&data, &listener, Capability::Previous)
+        .await
+        .unwrap());
+    assert!(!has_capability(&data, &listener, Capability::Volume)
+        .await
+        .unwrap());
+    assert!(
+        !has_capability(&data, &actor(STRANGER, false), Capability::Queue)
+            .await
+            .unwrap()
+    );
+}
+
+#[sqlx::test]
+async fn changes_need_spotify_connected(pool: sqlx::PgPool) {
+    let data = data(pool, None).await;
+
+    let denial = change_denial(&data, &actor(OWNER, true), Capability::Queue)
+        .await
+        .unwrap();
+    assert_eq!(denial, Some(Reason::Unauthorized));
+}
+
+#[sqlx::test]
+async fn changes_need_permission(pool: sqlx::PgPool) {
+    let spotify = FakeSpotify::spawn();
+    let data = data(pool, Some(&spotify)).await;
+
+    let denial = change_denial(&data, &actor(STRANGER, false), Capability::Queue)
+        .await
+        .unwrap();
+    assert_eq!(denial, Some(Reason::NoPermission));
+}
+
+#[sqlx::test]
+async fn playback_changes_need_something_playing(pool: sqlx::PgPool) {
+    let spotify = FakeSpotify::spawn();
+    let data = data(pool, Some(&spotify)).await;
+    let admin = actor(OWNER, true);
+
+    let denial = playback_denial(&data, &admin, Capability::Skip)
+        .await
+        .unwrap();
+    assert_eq!(denial, Some(Reason::NothingPlaying));
+
+    spotify.play(common::track("4uLU6hMCjMI75M1A2tKUQC", "Song", 200_000), 0);
+    let denial = playback_denial(&data, &admin, Capability::Skip)
+        .await
+        .unwrap();
+    assert_eq!(denial, None);
+}
+
+#[sqlx::test]
+async fn roles_can_only_be_given_below_your_own(pool: sqlx::PgPool) {
+    db_add_user(
+        &pool,
+        GUILD.get() as i64,
+        LISTENER.get() as i64,
+        "moderator",
+    )
+    .await
+    .unwrap();
+    let roles = db_get_roles(&pool).await.unwrap();
+    let role = |name: &str| roles.iter().find(|v| v.name == name).unwrap();
+    let data = data(pool, None).await;
+    let moderator = actor(LISTENER, false);
+
+    assert!(can_assign(&data, &moderator, role("dj")).await.unwrap());
+    assert!(!can_assign(&data, &moderator, role("moderator"))
+        .await
+        .unwrap());
+    assert!(can_assign(&data, &actor(OWNER, true), role("moderator"))
+        .await
+        .unwrap());
+    assert!(!can_assign(&data, &actor(STRANGER, false), role("guest"))
+        .await
+        .unwrap());
+}
+
+#[sqlx::test]
+async fn users_can_only_be_changed_below_your_own_role(pool: sqlx::PgPool) {
+    let guild = GUILD.get() as i64;
+    db_add_user(&pool, guild, LISTENER.get() as i64, "moderator")
+        .await
+        .unwrap();
+    db_add_user(&pool, guild, STRANGER.get() as i64, "moderator")
+        .await
+        .unwrap();
+    let data = data(pool, None).await;
+    let moderator = actor(LISTENER, false);
+
+    assert!(!can_manage(&data, &moderator, STRANGER, &[]).await.unwrap());
+    assert!(can_manage(&data, &moderator, OWNER, &[]).await.unwrap());
+    assert!(can_manage(&data, &actor(OWNER, true), STRANGER, &[])
+        .await
+        .unwrap());
+}
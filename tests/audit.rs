@@ -0,0 +1,61 @@
+mod common;
+
+use chrono::{TimeDelta, Utc};
+use common::{actor, data, GUILD, LISTENER, OWNER};
+use delegatify::access::has_capability;
+use delegatify::audit::{record, AuditAction, Event};
+use delegatify::database::{db_add_user, db_get_audit};
+use delegatify::permissions::Capability;
+
+#[sqlx::test]
+async fn audit_log_filters(pool: sqlx::PgPool) {
+    let guild = GUILD.get() as i64;
+    let freeze = Event::new(AuditAction::Freeze).from("Off").to("On");
+    let volume = Event::new(AuditAction::Volume).from("50%").to("80%");
+    record(&pool, GUILD, OWNER, &freeze).await.unwrap();
+    record(&pool, GUILD, LISTENER, &volume).await.unwrap();
+
+    let everything = db_get_audit(&pool, guild, None, None, None, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(everything.len(), 2);
+    // Newest first
+    assert_eq!(everything[0].action, "volume");
+
+    let by_owner = db_get_audit(&pool, guild, Some(OWNER.get() as i64), None, None, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(by_owner.len(), 1);
+    assert_eq!(by_owner[0].old_value.as_deref(), Some("Off"));
+    assert_eq!(by_owner[0].new_value.as_deref(), Some("On"));
+
+    let volume = db_get_audit(&pool, guild, None, Some("volume"), None, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(volume.len(), 1);
+    assert_eq!(volume[0].actor_id, LISTENER.get() as i64);
+
+    let tomorrow = Utc::now() + TimeDelta::days(1);
+    let later = db_get_audit(&pool, guild, None, None, Some(tomorrow), None, 10)
+        .await
+        .unwrap();
+    assert!(later.is_empty());
+}
+
+#[sqlx::test]
+async fn only_owners_view_audit(pool: sqlx::PgPool) {
+    let guild = GUILD.get() as i64;
+    db_add_user(&pool, guild, LISTENER.get() as i64, "moderator")
+        .await
+        .unwrap();
+
+    let data = data(pool, None).await;
+    let moderator = has_capability(&data, &actor(LISTENER, false), Capability::ViewAudit)
+        .await
+        .unwrap();
+    assert!(!moderator);
+    let owner = has_capability(&data, &actor(OWNER, true), Capability::ViewAudit)
+        .await
+        .unwrap();
+    assert!(owner);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::time::Instant;
+
+use common::{FakeSpotify, GUILD, LISTENER, OWNER};
+use delegatify::callback::{redirect_path, start, take, PendingAuth, PendingAuths, AUTH_TIMEOUT};
+use poise::serenity_prelude::{ChannelId, UserId};
+
+async fn pending(spotify: &FakeSpotify, user_id: UserId, started: Instant) -> PendingAuth {
+    PendingAuth {
+        guild_id: GUILD,
+        user_id,
+        channel_id: ChannelId::new(1),
+        client: spotify.client().await,
+        started,
+    }
+}
+
+#[tokio::test]
+async fn states_only_work_once() {
+    let spotify = FakeSpotify::spawn();
+    let pending_auth = PendingAuths::default();
+    let auth = pending(&spotify, OWNER, Instant::now()).await;
+    start(&pending_auth, "state".to_string(), auth).await;
+
+    assert!(take(&pending_auth, "other").await.is_none());
+    let auth = take(&pending_auth, "state").await.unwrap();
+    assert_eq!(auth.user_id, OWNER);
+    assert!(take(&pending_auth, "state").await.is_none());
+}
+
+#[tokio::test]
+async fn states_expire_and_are_replaced() {
+    let spotify = FakeSpotify::spawn();
+    let pending_auth = PendingAuths::default();
+    let expired = Instant::now() - AUTH_TIMEOUT;
+    let auth = pending(&spotify, LISTENER, expired).await;
+    start(&pending_auth, "expired".to_string(), auth).await;
+    assert!(take(&pending_auth, "expired").await.is_none());
+
+    // Running /authenticate again replaces the earlier flow
+    let first = pending(&spotify, OWNER, Instant::now()).await;
+    start(&pending_auth, "first".to_string(), first).await;
+    let second = pending(&spotify, OWNER, Instant::now()).await;
+    start(&pending_auth, "second".to_string(), second).await;
+    assert!(take(&pending_auth, "first").await.is_none());
+    assert!(take(&pending_auth, "second").await.is_some());
+}
+
+#[test]
+fn callback_path_comes_from_the_redirect_uri() {
+    assert_eq!(redirect_path("https://example.com/callback"), "/callback");
+    assert_eq!(
+        redirect_path("http://localhost:8000/spotify/callback?x=1"),
+        "/spotify/callback"
+    );
+    assert_eq!(redirect_path("https://example.com"), "/");
+}
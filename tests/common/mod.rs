@@ -0,0 +1,318 @@
+//! A fake Spotify Web API and helpers shared by the integration tests
+#![allow(dead_code)]
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Json, Router,
+};
+use chrono::{TimeDelta, Utc};
+use delegatify::spotify::SpotifyLink;
+use delegatify::{access::Actor, crypto::TokenCipher, database::db_add_user, Data};
+use poise::serenity_prelude::{GuildId, UserId};
+use rspotify::{prelude::BaseClient, AuthCodePkceSpotify, Config, Credentials, OAuth, Token};
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+
+pub const GUILD: GuildId = GuildId::new(1);
+pub const OWNER: UserId = UserId::new(10);
+pub const LISTENER: UserId = UserId::new(11);
+pub const STRANGER: UserId = UserId::new(12);
+
+pub const PLAYING: &str = "4uLU6hMCjMI75M1A2tKUQC";
+pub const REQUESTED: &str = "7ouMYWpwJ422jRcDASZB7P";
+
+/// What the fake Spotify account is doing
+#[derive(Default)]
+pub struct SpotifyState {
+    /// The playing track, with its progress in milliseconds and whether it is paused
+    pub playing: Option<(Value, u64, bool)>,
+    pub queue: Vec<Value>,
+    /// Tracks that can be looked up or searched, by id
+    pub tracks: HashMap<String, Value>,
+    /// URIs sent to the queue, oldest first
+    pub added: Vec<String>,
+    /// Devices playback can start on
+    pub devices: Vec<Value>,
+    /// URIs started on a device, with the device's id
+    pub started: Vec<(String, String)>,
+}
+
+/// A local server answering the Spotify endpoints the bot uses
+pub struct FakeSpotify {
+    pub url: String,
+    pub state: Arc<Mutex<SpotifyState>>,
+}
+
+impl FakeSpotify {
+    /// Starts the server on a free port; It stops with the test's runtime
+    pub fn spawn() -> Self {
+        let state = Arc::new(Mutex::new(SpotifyState::default()));
+        let app = Router::new()
+            .route("/me/player/currently-playing", get(currently_playing))
+            .route("/me/player/devices", get(devices))
+            .route("/me/player/play", put(start_playback))
+            .route("/me/player/queue", get(queue).post(add_to_queue))
+            .route("/tracks/:id", get(track_by_id))
+            .route("/search", get(search))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+
+        Self { url, state }
+    }
+
+    /// Makes the track available to look up and search
+    pub fn add_track(&self, track: Value) {
+        let id = track["id"].as_str().unwrap().to_string();
+        self.state.lock().unwrap().tracks.insert(id, track);
+    }
+
+    pub fn play(&self, track: Value, progress_ms: u64) {
+        self.state.lock().unwrap().playing = Some((track, progress_ms, true));
+    }
+
+    pub fn added(&self) -> Vec<String> {
+        self.state.lock().unwrap().added.clone()
+    }
+
+    /// Makes a device available to start playback on
+    pub fn add_device(&self, id: &str, name: &str) {
+        self.state.lock().unwrap().devices.push(json!({
+            "id": id,
+            "is_active": false,
+            "is_private_session": false,
+            "is_restricted": false,
+            "name": name,
+            "type": "Speaker",
+            "volume_percent": 50,
+        }));
+    }
+
+    pub fn started(&self) -> Vec<(String, String)> {
+        self.state.lock().unwrap().started.clone()
+    }
+
+    /// A client authenticated against this server
+    pub async fn client(&self) -> AuthCodePkceSpotify {
+        let config = Config {
+            api_base_url: self.url.clone(),
+            token_refreshing: false,
+            ..Default::default()
+        };
+        let oauth = OAuth {
+            redirect_uri: "http://localhost/callback".to_string(),
+            ..Default::default()
+        };
+        let client = AuthCodePkceSpotify::with_config(Credentials::new_pkce("test"), oauth, config);
+
+        let token = Token {
+            access_token: "test".to_string(),
+            expires_in: TimeDelta::hours(1),
+            expires_at: Some(Utc::now() + TimeDelta::hours(1)),
+            ..Default::default()
+        };
+        *client.get_token().lock().await.unwrap() = Some(token);
+        client
+    }
+}
+
+type SharedState = State<Arc<Mutex<SpotifyState>>>;
+
+async fn currently_playing(State(state): SharedState) -> Response {
+    match &state.lock().unwrap().playing {
+        Some((track, progress, is_playing)) => Json(json!({
+            "context": null,
+            "timestamp": Utc::now().timestamp_millis(),
+            "progress_ms": progress,
+            "is_playing": is_playing,
+            "item": track,
+            "currently_playing_type": "track",
+            "actions": { "disallows": {} },
+        }))
+        .into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+async fn queue(State(state): SharedState) -> Json<Value> {
+    let state = state.lock().unwrap();
+    Json(json!({
+        "currently_playing": state.playing.as_ref().map(|v| v.0.clone()),
+        "queue": state.queue,
+    }))
+}
+
+async fn add_to_queue(
+    State(state): SharedState,
+    Query(params): Query<HashMap<String, String>>,
+) -> StatusCode {
+    let uri = params["uri"].clone();
+    let mut state = state.lock().unwrap();
+    let id = uri.rsplit(':').next().unwrap_or_default();
+    if let Some(track) = state.tracks.get(id).cloned() {
+        state.queue.push(track);
+    }
+    state.added.push(uri);
+    StatusCode::NO_CONTENT
+}
+
+async fn devices(State(state): SharedState) -> Json<Value> {
+    Json(json!({ "devices": state.lock().unwrap().devices }))
+}
+
+async fn start_playback(
+    State(state): SharedState,
+    Query(params): Query<HashMap<String, String>>,
+    Json(body): Json<Value>,
+) -> StatusCode {
+    let device_id = params.get("device_id").cloned().unwrap_or_default();
+    let mut state = state.lock().unwrap();
+
+    // Restricted devices don't accept commands
+    let restricted = state
+        .devices
+        .iter()
+        .any(|v| v["id"] == device_id.as_str() && v["is_restricted"] == true);
+    if restricted {
+        return StatusCode::FORBIDDEN;
+    }
+
+    for uri in body["uris"].as_array().into_iter().flatten() {
+        let uri = uri.as_str().unwrap_or_default().to_string();
+        let id = uri.rsplit(':').next().unwrap_or_default();
+        if let Some(track) = state.tracks.get(id).cloned() {
+            state.playing = Some((track, 0, true));
+        }
+        state.started.push((uri, device_id.clone()));
+    }
+    StatusCode::NO_CONTENT
+}
+
+async fn track_by_id(State(state): SharedState, Path(id): Path<String>) -> Response {
+    match state.lock().unwrap().tracks.get(&id) {
+        Some(track) => Json(track.clone()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn search(
+    State(state): SharedState,
+    Query(params): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    let query = params["q"].to_lowercase();
+    let items = state
+        .lock()
+        .unwrap()
+        .tracks
+        .values()
+        .filter(|v| v["name"].as_str().unwrap().to_lowercase().contains(&query))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(json!({
+        "tracks": {
+            "href": "",
+            "items": items,
+            "limit": 10,
+            "next": null,
+            "offset": 0,
+            "previous": null,
+            "total": items.len(),
+        }
+    }))
+}
+
+/// A track as Spotify returns it; Ids are 22 characters like real ones
+pub fn track(id: &str, name: &str, duration_ms: u64) -> Value {
+    json!({
+        "album": {
+            "album_type": "album",
+            "artists": [],
+            "external_urls": {},
+            "href": null,
+            "id": "2up3OPMp9Tb4dAKM2erWXQ",
+            "images": [],
+            "name": "Album",
+        },
+        "artists": [{
+            "external_urls": {},
+            "href": null,
+            "id": "0OdUWJ0sBjDrqHygGUXeCF",
+            "name": "Artist",
+        }],
+        "disc_number": 1,
+        "duration_ms": duration_ms,
+        "explicit": false,
+        "external_ids": {},
+        "external_urls": { "spotify": format!("https://open.spotify.com/track/{id}") },
+        "href": null,
+        "id": id,
+        "is_local": false,
+        "name": name,
+        "popularity": 50,
+        "preview_url": null,
+        "track_number": 1,
+        "type": "track",
+    })
+}
+
+/// Bot state with the guild connected to the fake server, if there is one
+pub async fn data(pool: sqlx::PgPool, spotify: Option<&FakeSpotify>) -> Data {
+    let mut clients = HashMap::new();
+    if let Some(spotify) = spotify {
+        clients.insert(GUILD, spotify.client().await);
+    }
+
+    Data {
+        spotify: Arc::new(RwLock::new(clients)),
+        pool,
+        cipher: TokenCipher::new("test"),
+        requests: Default::default(),
+        skip_votes: RwLock::new(HashSet::new()),
+        search_cache: RwLock::new(HashMap::new()),
+        typing: RwLock::new(HashMap::new()),
+        live_messages: Default::default(),
+        pending_auth: Default::default(),
+    }
+}
+
+/// Plays a song, with another ready to be requested by the listener
+pub async fn setup(pool: &sqlx::PgPool) -> FakeSpotify {
+    db_add_user(pool, GUILD.get() as i64, LISTENER.get() as i64, "listener")
+        .await
+        .unwrap();
+
+    let spotify = FakeSpotify::spawn();
+    spotify.add_track(track(PLAYING, "Playing", 200_000));
+    spotify.add_track(track(REQUESTED, "Requested", 180_000));
+    spotify.play(track(PLAYING, "Playing", 200_000), 0);
+    spotify
+}
+
+/// The link the listener requests
+pub fn requested_link() -> SpotifyLink {
+    SpotifyLink::parse(&format!("https://open.spotify.com/track/{REQUESTED}")).unwrap()
+}
+
+pub fn actor(user_id: UserId, is_admin: bool) -> Actor {
+    Actor {
+        guild_id: GUILD,
+        user_id,
+        roles: Vec::new(),
+        is_admin,
+    }
+}
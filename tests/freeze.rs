@@ -0,0 +1,108 @@
+mod common;
+
+use chrono::{NaiveTime, TimeDelta, TimeZone, Utc};
+use common::{actor, data, FakeSpotify, GUILD, OWNER};
+use delegatify::access::{change_denial, Reason};
+use delegatify::database::{
+    db_add_freeze_schedule, db_get_freeze_state, db_get_settings, db_save_settings, FreezeSchedule,
+};
+use delegatify::freeze::{in_window, is_frozen, parse_days};
+use delegatify::permissions::Capability;
+
+#[sqlx::test]
+async fn freezing_stops_everyone(pool: sqlx::PgPool) {
+    let guild = GUILD.get() as i64;
+    let mut settings = db_get_settings(&pool, guild).await.unwrap();
+    settings.frozen = true;
+    db_save_settings(&pool, guild, &settings).await.unwrap();
+
+    let spotify = FakeSpotify::spawn();
+    let data = data(pool, Some(&spotify)).await;
+    let denial = change_denial(&data, &actor(OWNER, true), Capability::Queue)
+        .await
+        .unwrap();
+    assert_eq!(denial, Some(Reason::Frozen));
+}
+
+#[sqlx::test]
+async fn freezing_ends_by_itself(pool: sqlx::PgPool) {
+    let guild = GUILD.get() as i64;
+    let mut settings = db_get_settings(&pool, guild).await.unwrap();
+    settings.frozen = true;
+    settings.frozen_until = Some(Utc::now() + TimeDelta::hours(1));
+    db_save_settings(&pool, guild, &settings).await.unwrap();
+    assert!(is_frozen(&pool, GUILD).await.unwrap());
+
+    settings.frozen_until = Some(Utc::now() - TimeDelta::minutes(1));
+    db_save_settings(&pool, guild, &settings).await.unwrap();
+    assert!(!is_frozen(&pool, GUILD).await.unwrap());
+}
+
+#[sqlx::test]
+async fn schedules_freeze_inside_their_window(pool: sqlx::PgPool) {
+    assert!(!is_frozen(&pool, GUILD).await.unwrap());
+
+    // Starting and ending at midnight covers the whole day
+    let midnight = NaiveTime::MIN;
+    db_add_freeze_schedule(
+        &pool,
+        GUILD.get() as i64,
+        parse_days("daily").unwrap(),
+        midnight,
+        midnight,
+        "UTC",
+    )
+    .await
+    .unwrap();
+    assert!(is_frozen(&pool, GUILD).await.unwrap());
+}
+
+#[sqlx::test]
+async fn settings_and_schedules_load_together(pool: sqlx::PgPool) {
+    let guild = GUILD.get() as i64;
+    assert!(!db_get_freeze_state(&pool, guild).await.unwrap().0);
+
+    let mut settings = db_get_settings(&pool, guild).await.unwrap();
+    settings.frozen = true;
+    db_save_settings(&pool, guild, &settings).await.unwrap();
+    for timezone in ["UTC", "Europe/Paris"] {
+        let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        db_add_freeze_schedule(
+            &pool,
+            guild,
+            parse_days("weekends").unwrap(),
+            noon,
+            noon,
+            timezone,
+        )
+        .await
+        .unwrap();
+    }
+
+    let (frozen, schedules) = db_get_freeze_state(&pool, guild).await.unwrap();
+    assert!(frozen);
+    let timezones = schedules
+        .iter()
+        .map(|v| v.timezone.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(timezones, vec!["UTC", "Europe/Paris"]);
+}
+
+#[test]
+fn windows_can_cross_midnight() {
+    let schedule = FreezeSchedule {
+        id: 1,
+        days: parse_days("fri").unwrap(),
+        starts: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+        ends: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+        timezone: "America/New_York".to_string(),
+    };
+
+    // Friday 2025-01-03 23:00 and Saturday 01:00 in New York
+    let friday = Utc.with_ymd_and_hms(2025, 1, 4, 4, 0, 0).unwrap();
+    let saturday = Utc.with_ymd_and_hms(2025, 1, 4, 6, 0, 0).unwrap();
+    let saturday_night = Utc.with_ymd_and_hms(2025, 1, 5, 4, 0, 0).unwrap();
+    assert!(in_window(&schedule, friday));
+    assert!(in_window(&schedule, saturday));
+    assert!(!in_window(&schedule, saturday_night));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{GUILD, LISTENER, OWNER};
+use delegatify::crypto::TokenCipher;
+use delegatify::database::{
+    db_add_user, db_adopt_legacy_rows, db_get_token_owner, db_get_tokens, db_get_user_role,
+    db_save_token, db_user_exists,
+};
+use delegatify::spotify::restore;
+use rspotify::Token;
+
+#[sqlx::test]
+async fn adopting_keeps_the_guilds_own_rows(pool: sqlx::PgPool) {
+    let guild = GUILD.get() as i64;
+    let (owner, listener) = (OWNER.get() as i64, LISTENER.get() as i64);
+    db_add_user(&pool, 0, owner, "listener").await.unwrap();
+    db_add_user(&pool, 0, listener, "listener").await.unwrap();
+    db_save_token(&pool, 0, None, b"legacy").await.unwrap();
+
+    // The guild authenticated and added the owner again before the legacy rows were adopted
+    db_add_user(&pool, guild, owner, "moderator").await.unwrap();
+    db_save_token(&pool, guild, Some(owner), b"new")
+        .await
+        .unwrap();
+
+    db_adopt_legacy_rows(&pool, guild).await.unwrap();
+
+    let role = db_get_user_role(&pool, guild, owner, &[]).await.unwrap();
+    assert_eq!(role.unwrap().name, "moderator");
+    assert!(db_user_exists(&pool, guild, listener).await.unwrap());
+    assert!(!db_user_exists(&pool, 0, listener).await.unwrap());
+
+    let tokens = db_get_tokens(&pool).await.unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].2, b"new");
+    assert_eq!(db_get_token_owner(&pool, guild).await.unwrap(), Some(owner));
+}
+
+#[sqlx::test]
+async fn legacy_tokens_are_skipped_until_adopted(pool: sqlx::PgPool) {
+    let cipher = TokenCipher::new("test");
+    let token = serde_json::to_vec(&Token::default()).unwrap();
+    let data = cipher.encrypt(&token).unwrap();
+    db_save_token(&pool, 0, None, &data).await.unwrap();
+
+    let clients = restore(&pool, &cipher).await.unwrap();
+    assert!(clients.is_empty());
+}
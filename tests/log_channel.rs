@@ -0,0 +1,37 @@
+mod common;
+
+use common::GUILD;
+use delegatify::audit::AuditAction;
+use delegatify::database::{db_get_log_settings, db_save_log_settings};
+use delegatify::log_channel::LogEvent;
+
+#[sqlx::test]
+async fn log_settings_are_saved(pool: sqlx::PgPool) {
+    let guild = GUILD.get() as i64;
+    let mut settings = db_get_log_settings(&pool, guild).await.unwrap();
+    assert_eq!(settings.channel_id, None);
+    assert!(LogEvent::ALL.iter().all(|v| v.is_enabled(&settings)));
+
+    settings.channel_id = Some(42);
+    LogEvent::Skip.set_enabled(&mut settings, false);
+    db_save_log_settings(&pool, guild, &settings).await.unwrap();
+
+    let saved = db_get_log_settings(&pool, guild).await.unwrap();
+    assert_eq!(saved.channel_id, Some(42));
+    assert!(!LogEvent::Skip.is_enabled(&saved));
+    assert!(LogEvent::Previous.is_enabled(&saved));
+}
+
+#[test]
+fn only_some_actions_are_posted() {
+    assert_eq!(LogEvent::of(AuditAction::Play), Some(LogEvent::Queue));
+    assert_eq!(
+        LogEvent::of(AuditAction::FreezeSchedule),
+        Some(LogEvent::Freeze)
+    );
+    assert_eq!(
+        LogEvent::of(AuditAction::GrantRole),
+        Some(LogEvent::Permissions)
+    );
+    assert_eq!(LogEvent::of(AuditAction::Volume), None);
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::{
+    data, requested_link, setup, track, FakeSpotify, GUILD, LISTENER, PLAYING, REQUESTED,
+};
+use delegatify::database::{
+    db_add_block, db_add_play, db_get_settings, db_remove_waiting_plays, db_save_settings,
+};
+use delegatify::policy::QueuePolicy;
+use delegatify::poller::{poll_guild, GuildState};
+use delegatify::requests::Request;
+use delegatify::spotify::{fetch_link, fetch_queue, StandardItem};
+
+/// Fetches the listener's request and records it as waiting
+async fn requested(pool: &sqlx::PgPool, spotify: &FakeSpotify) -> StandardItem<'static> {
+    let mut found = fetch_link(&spotify.client().await, &requested_link(), 25)
+        .await
+        .unwrap();
+    let item = found.items.remove(0);
+    db_add_play(pool, GUILD.get() as i64, &item, LISTENER.get() as i64, true)
+        .await
+        .unwrap();
+    item
+}
+
+async fn request(data: &delegatify::Data, item: StandardItem<'static>) {
+    data.requests
+        .write()
+        .await
+        .entry(GUILD)
+        .or_default()
+        .push(Request {
+            item,
+            requester: LISTENER,
+        });
+}
+
+#[sqlx::test]
+async fn links_are_fetched(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let item = requested(&pool, &spotify).await;
+
+    assert_eq!(item.get_title(), "Requested - Artist");
+    assert_eq!(item.uri(), format!("spotify:track:{REQUESTED}"));
+    assert_eq!(item.artist_ids, vec!["0OdUWJ0sBjDrqHygGUXeCF"]);
+}
+
+#[sqlx::test]
+async fn requests_are_released_as_the_song_ends(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let item = requested(&pool, &spotify).await;
+    let data = data(pool.clone(), Some(&spotify)).await;
+    let client = spotify.client().await;
+    request(&data, item).await;
+
+    // Plenty of the song is left, so the request can still be moved or removed
+    let mut state = GuildState::default();
+    poll_guild(&pool, GUILD, &client, &data.requests, &mut state)
+        .await
+        .unwrap();
+    assert!(spotify.added().is_empty());
+
+    spotify.play(track(PLAYING, "Playing", 200_000), 190_000);
+    poll_guild(&pool, GUILD, &client, &data.requests, &mut state)
+        .await
+        .unwrap();
+    assert_eq!(spotify.added(), vec![format!("spotify:track:{REQUESTED}")]);
+    assert_eq!(fetch_queue(&client).await.unwrap().len(), 1);
+
+    // Only one request waits in Spotify's queue at a time
+    request(&data, requested(&pool, &spotify).await).await;
+    spotify.play(track(PLAYING, "Playing", 200_000), 195_000);
+    poll_guild(&pool, GUILD, &client, &data.requests, &mut state)
+        .await
+        .unwrap();
+    assert_eq!(spotify.added().len(), 1);
+}
+
+#[sqlx::test]
+async fn waiting_requests_are_removed_on_restart(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let item = requested(&pool, &spotify).await;
+    let data = data(pool.clone(), Some(&spotify)).await;
+    let client = spotify.client().await;
+    request(&data, item).await;
+
+    // Sent to Spotify, so it's still in the queue after a restart
+    spotify.play(track(PLAYING, "Playing", 200_000), 190_000);
+    let mut state = GuildState::default();
+    poll_guild(&pool, GUILD, &client, &data.requests, &mut state)
+        .await
+        .unwrap();
+    requested(&pool, &spotify).await;
+
+    assert_eq!(db_remove_waiting_plays(&pool).await.unwrap(), 1);
+    let left: Vec<(String,)> =
+        sqlx::query_as("SELECT track_id FROM plays WHERE requester IS NOT NULL")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(left, vec![(format!("spotify:track:{REQUESTED}"),)]);
+}
+
+#[sqlx::test]
+async fn playing_songs_are_recorded(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    requested(&pool, &spotify).await;
+    let data = data(pool.clone(), Some(&spotify)).await;
+    let client = spotify.client().await;
+
+    let mut state = GuildState::default();
+    poll_guild(&pool, GUILD, &client, &data.requests, &mut state)
+        .await
+        .unwrap();
+
+    let started: Vec<(String,)> =
+        sqlx::query_as("SELECT track_id FROM plays WHERE started_at IS NOT NULL")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(started, vec![(format!("spotify:track:{PLAYING}"),)]);
+}
+
+#[sqlx::test]
+async fn blocked_requests_are_dropped(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let item = requested(&pool, &spotify).await;
+    let data = data(pool.clone(), Some(&spotify)).await;
+    let client = spotify.client().await;
+    request(&data, item).await;
+
+    // Blocked after it was requested
+    db_add_block(&pool, GUILD.get() as i64, "track", REQUESTED, "Requested")
+        .await
+        .unwrap();
+    spotify.play(track(PLAYING, "Playing", 200_000), 190_000);
+
+    let mut state = GuildState::default();
+    poll_guild(&pool, GUILD, &client, &data.requests, &mut state)
+        .await
+        .unwrap();
+    assert!(spotify.added().is_empty());
+    assert!(data.requests.read().await[&GUILD].get(0).is_none());
+
+    let requested: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM plays WHERE requester IS NOT NULL")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(requested.0, 0);
+}
+
+#[sqlx::test]
+async fn policy_limits_song_length(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let item = requested(&pool, &spotify).await;
+
+    let policy = QueuePolicy::load(&pool, GUILD).await.unwrap();
+    assert_eq!(policy.reason(&item), None);
+
+    let guild = GUILD.get() as i64;
+    let mut settings = db_get_settings(&pool, guild).await.unwrap();
+    settings.max_duration = Some(120);
+    db_save_settings(&pool, guild, &settings).await.unwrap();
+
+    let policy = QueuePolicy::load(&pool, GUILD).await.unwrap();
+    assert_eq!(
+        policy.reason(&item),
+        Some("Longer than the 02:00 limit".to_string())
+    );
+}
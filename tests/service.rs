@@ -0,0 +1,139 @@
+mod common;
+
+use common::{
+    actor, data, requested_link, setup, FakeSpotify, GUILD, LISTENER, REQUESTED, STRANGER,
+};
+use delegatify::access::Reason;
+use delegatify::database::{
+    db_add_block, db_save_settings, db_set_user_quota, GuildSettings, Quota,
+};
+use delegatify::service::{queue_link, queue_view, Position, QueueOutcome};
+
+#[sqlx::test]
+async fn songs_wait_for_their_turn(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let link = requested_link();
+    let data = data(pool, Some(&spotify)).await;
+
+    let queued = match queue_link(&data, &actor(LISTENER, false), &link)
+        .await
+        .unwrap()
+    {
+        QueueOutcome::Added(v) => v,
+        _ => panic!("The song wasn't queued"),
+    };
+    assert_eq!(queued.position, Position::Waiting(1));
+    assert_eq!(queued.items[0].get_title(), "Requested - Artist");
+
+    let client = spotify.client().await;
+    let view = queue_view(&data, GUILD, &client).await.unwrap().unwrap();
+    assert_eq!(view.waiting.len(), 1);
+    assert_eq!(view.remaining.num_seconds(), 200 + 180);
+}
+
+#[sqlx::test]
+async fn queuing_needs_permission(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let link = requested_link();
+    let data = data(pool, Some(&spotify)).await;
+
+    let outcome = queue_link(&data, &actor(STRANGER, false), &link)
+        .await
+        .unwrap();
+    assert!(matches!(
+        outcome,
+        QueueOutcome::Denied(Reason::NoPermission)
+    ));
+}
+
+#[sqlx::test]
+async fn blocked_songs_are_refused(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let link = requested_link();
+    db_add_block(&pool, GUILD.get() as i64, "track", REQUESTED, "Requested")
+        .await
+        .unwrap();
+    let data = data(pool, Some(&spotify)).await;
+
+    match queue_link(&data, &actor(LISTENER, false), &link)
+        .await
+        .unwrap()
+    {
+        QueueOutcome::Blocked { reason, .. } => assert_eq!(reason, "This song is blocked"),
+        _ => panic!("The song wasn't blocked"),
+    }
+}
+
+#[sqlx::test]
+async fn quotas_limit_requests(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let link = requested_link();
+    let quota = Quota {
+        max_requests: 1,
+        period: 3600,
+    };
+    db_set_user_quota(&pool, GUILD.get() as i64, LISTENER.get() as i64, &quota)
+        .await
+        .unwrap();
+    let data = data(pool, Some(&spotify)).await;
+    let listener = actor(LISTENER, false);
+
+    let first = queue_link(&data, &listener, &link).await.unwrap();
+    assert!(matches!(first, QueueOutcome::Added(_)));
+
+    let second = queue_link(&data, &listener, &link).await.unwrap();
+    assert!(matches!(
+        second,
+        QueueOutcome::Denied(Reason::Quota { used: 1, .. })
+    ));
+}
+
+/// Stops playback, leaving the preferred device to start on
+async fn idle(pool: &sqlx::PgPool, spotify: &FakeSpotify) {
+    spotify.state.lock().unwrap().playing = None;
+    spotify.add_device("speaker", "Speaker");
+    let settings = GuildSettings {
+        preferred_device: Some("speaker".to_string()),
+        ..Default::default()
+    };
+    db_save_settings(pool, GUILD.get() as i64, &settings)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn songs_start_on_the_preferred_device(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let link = requested_link();
+    idle(&pool, &spotify).await;
+    let data = data(pool, Some(&spotify)).await;
+
+    let queued = match queue_link(&data, &actor(LISTENER, false), &link)
+        .await
+        .unwrap()
+    {
+        QueueOutcome::Added(v) => v,
+        _ => panic!("The song wasn't queued"),
+    };
+    assert_eq!(queued.position, Position::PlayingNow);
+    let uri = format!("spotify:track:{REQUESTED}");
+    assert_eq!(spotify.started(), vec![(uri, "speaker".to_string())]);
+}
+
+#[sqlx::test]
+async fn songs_that_fail_to_start_are_not_recorded(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let link = requested_link();
+    idle(&pool, &spotify).await;
+    spotify.state.lock().unwrap().devices[0]["is_restricted"] = true.into();
+    let data = data(pool.clone(), Some(&spotify)).await;
+
+    assert!(queue_link(&data, &actor(LISTENER, false), &link)
+        .await
+        .is_err());
+    let plays: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM plays")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(plays.0, 0);
+}
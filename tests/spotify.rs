@@ -0,0 +1,99 @@
+mod common;
+
+use std::{env, time::Duration};
+
+use chrono::{TimeDelta, Utc};
+use common::{data, setup, GUILD, OWNER, REQUESTED};
+use delegatify::crypto::TokenCipher;
+use delegatify::database::{db_add_block, db_get_tokens};
+use delegatify::spotify::{fetch_queue, init, restore, save_token, search_tracks};
+use rspotify::prelude::OAuthClient;
+use rspotify::Token;
+
+#[sqlx::test]
+async fn search_suggests_songs(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    let data = data(pool, Some(&spotify)).await;
+
+    let found = search_tracks(&data, GUILD, "req").await.unwrap();
+    let uri = format!("spotify:track:{REQUESTED}");
+    assert_eq!(found, vec![("Requested - Artist".to_string(), uri)]);
+
+    // Later searches are answered from the cache
+    spotify.state.lock().unwrap().tracks.clear();
+    assert_eq!(search_tracks(&data, GUILD, "Req").await.unwrap().len(), 1);
+
+    // Even cached, blocked songs aren't suggested
+    db_add_block(
+        &data.pool,
+        GUILD.get() as i64,
+        "track",
+        REQUESTED,
+        "Requested",
+    )
+    .await
+    .unwrap();
+    assert!(search_tracks(&data, GUILD, "req").await.unwrap().is_empty());
+}
+
+/// Sets the credentials `init` reads; Every test sets the same ones, since they share the environment
+fn credentials() {
+    env::set_var("RSPOTIFY_CLIENT_ID", "test");
+    env::set_var("RSPOTIFY_REDIRECT_URI", "http://localhost/callback");
+}
+
+// The only test here that sets the API URL
+#[sqlx::test]
+async fn restored_clients_use_the_api_url(pool: sqlx::PgPool) {
+    let spotify = setup(&pool).await;
+    credentials();
+    env::set_var("SPOTIFY_API_URL", &spotify.url);
+
+    let cipher = TokenCipher::new("test");
+    let token = Token {
+        access_token: "test".to_string(),
+        expires_in: TimeDelta::hours(1),
+        expires_at: Some(Utc::now() + TimeDelta::hours(1)),
+        ..Default::default()
+    };
+    save_token(&pool, &cipher, GUILD, Some(OWNER), &token)
+        .await
+        .unwrap();
+
+    let clients = restore(&pool, &cipher).await.unwrap();
+    let playing = clients[&GUILD]
+        .current_playing(None, None::<Vec<_>>)
+        .await
+        .unwrap();
+    assert!(playing.is_some());
+    assert!(fetch_queue(&clients[&GUILD]).await.unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn refreshed_tokens_are_saved_in_order(pool: sqlx::PgPool) {
+    credentials();
+    let cipher = TokenCipher::new("test");
+    let client = init(&pool, &cipher, GUILD, Some(OWNER)).await.unwrap();
+    let callback = client.config.token_callback_fn.as_ref().as_ref().unwrap();
+    for i in 0..20 {
+        let token = Token {
+            access_token: i.to_string(),
+            ..Default::default()
+        };
+        (callback.0)(token).unwrap();
+    }
+
+    // Tokens are saved in the background
+    let mut saved = String::new();
+    for _ in 0..50 {
+        if let Some((_, _, data)) = db_get_tokens(&pool).await.unwrap().pop() {
+            let token: Token = serde_json::from_slice(&cipher.decrypt(&data).unwrap()).unwrap();
+            saved = token.access_token;
+            if saved == "19" {
+                break;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(saved, "19");
+}
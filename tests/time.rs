@@ -0,0 +1,33 @@
+use chrono::TimeDelta;
+use delegatify::{format_delta, parse_delta};
+
+#[test]
+fn times_are_parsed() {
+    assert_eq!(parse_delta("45"), Some(TimeDelta::seconds(45)));
+    assert_eq!(parse_delta("1:30"), Some(TimeDelta::seconds(90)));
+    assert_eq!(parse_delta(" 1:02:03 "), Some(TimeDelta::seconds(3723)));
+    // The largest unit may go past 60
+    assert_eq!(parse_delta("75:00"), Some(TimeDelta::minutes(75)));
+    assert_eq!(parse_delta("90"), Some(TimeDelta::seconds(90)));
+
+    let time = TimeDelta::seconds(754);
+    assert_eq!(parse_delta(&format_delta(time)), Some(time));
+}
+
+#[test]
+fn overflowing_units_are_rejected() {
+    assert_eq!(parse_delta("1:60"), None);
+    assert_eq!(parse_delta("1:75:00"), None);
+    assert_eq!(parse_delta("1:00:60"), None);
+}
+
+#[test]
+fn malformed_times_are_rejected() {
+    assert_eq!(parse_delta(""), None);
+    assert_eq!(parse_delta("abc"), None);
+    assert_eq!(parse_delta("1:"), None);
+    assert_eq!(parse_delta(":30"), None);
+    assert_eq!(parse_delta("-5"), None);
+    assert_eq!(parse_delta("1.5"), None);
+    assert_eq!(parse_delta("1:2:3:4"), None);
+}